@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 /// Get the environment variable with the given name, panicking if it is not set.
@@ -87,6 +87,16 @@ fn main() {
             cfg.define("SANITIZE", "address");
         }
 
+        // Chimera is only built by Vectorscan when it is given a PCRE source tree to build
+        // alongside it.
+        if cfg!(feature = "chimera") {
+            let pcre_src_dir = pcre_source(&manifest_dir);
+            cfg.define("PCRE_SOURCE", &pcre_src_dir)
+                // The PCRE 8.x build declares compatibility with ancient versions of CMake, which
+                // newer versions of CMake refuse to configure without this.
+                .define("CMAKE_POLICY_VERSION_MINIMUM", "3.5");
+        }
+
         // NOTE: Several Vectorscan feature flags can be set based on available CPU SIMD features.
         // Enabling these according to availability on the build system CPU is fragile, however:
         // the resulting binary will not work correctly on machines with CPUs with different SIMD
@@ -147,7 +157,23 @@ fn main() {
 
         let dst = cfg.build();
 
+        // N.B. order matters here: Chimera depends on both Vectorscan and PCRE
+        if cfg!(feature = "chimera") {
+            println!("cargo:rustc-link-lib=static=chimera");
+        }
         println!("cargo:rustc-link-lib=static=hs");
+        if cfg!(feature = "chimera") {
+            // PCRE is built as a dependency of Chimera but is not installed, so it has to be
+            // picked up from the build tree. Depending on the profile, it may have a debug suffix.
+            let pcre_lib_dir = dst.join("build").join("lib");
+            let pcre_lib = if pcre_lib_dir.join("libpcred.a").exists() {
+                "pcred"
+            } else {
+                "pcre"
+            };
+            println!("cargo:rustc-link-lib=static={pcre_lib}");
+            println!("cargo:rustc-link-search={}", pcre_lib_dir.display());
+        }
         println!("cargo:rustc-link-search={}", dst.join("lib").display());
         println!("cargo:rustc-link-search={}", dst.join("lib64").display());
     }
@@ -177,10 +203,47 @@ fn main() {
             .expect("Unable to generate bindings")
             .write_to_file(out_dir.join("bindings.rs"))
             .expect("Failed to write Rust bindings to Vectorscan");
+
+        #[cfg(feature = "chimera")]
+        {
+            // The Chimera API refers to a few Vectorscan types, which are already generated above
+            let config = bindgen::Builder::default()
+                .allowlist_function("ch_.*")
+                .allowlist_type("ch_.*")
+                .allowlist_var("CH_.*")
+                .allowlist_recursively(false)
+                .header("wrapper_chimera.h")
+                .clang_arg(format!("-I{}", &include_dir));
+            config
+                .generate()
+                .expect("Unable to generate Chimera bindings")
+                .write_to_file(out_dir.join("chimera_bindings.rs"))
+                .expect("Failed to write Rust bindings to Chimera");
+        }
     }
     #[cfg(not(feature = "bindgen"))]
     {
         std::fs::copy("src/bindings.rs", out_dir.join("bindings.rs"))
             .expect("Failed to write Rust bindings to Vectorscan");
+
+        #[cfg(feature = "chimera")]
+        std::fs::copy(
+            "src/chimera_bindings.rs",
+            out_dir.join("chimera_bindings.rs"),
+        )
+        .expect("Failed to write Rust bindings to Chimera");
+    }
+}
+
+/// Get the vendored PCRE source tree to build Chimera with.
+fn pcre_source(manifest_dir: &Path) -> PathBuf {
+    let pcre_src_dir = manifest_dir.join("pcre");
+    println!("cargo:rerun-if-changed={}", pcre_src_dir.display());
+    if !pcre_src_dir.join("CMakeLists.txt").exists() {
+        panic!(
+            "The `chimera` feature needs the PCRE 8.x sources vendored in `{}`",
+            pcre_src_dir.display()
+        );
     }
+    pcre_src_dir
 }
@@ -0,0 +1,266 @@
+/* automatically generated by rust-bindgen 0.69.4 */
+
+pub const CH_SUCCESS: u32 = 0;
+pub const CH_INVALID: i32 = -1;
+pub const CH_NOMEM: i32 = -2;
+pub const CH_SCAN_TERMINATED: i32 = -3;
+pub const CH_COMPILER_ERROR: i32 = -4;
+pub const CH_DB_VERSION_ERROR: i32 = -5;
+pub const CH_DB_PLATFORM_ERROR: i32 = -6;
+pub const CH_DB_MODE_ERROR: i32 = -7;
+pub const CH_BAD_ALIGN: i32 = -8;
+pub const CH_BAD_ALLOC: i32 = -9;
+pub const CH_SCRATCH_IN_USE: i32 = -10;
+pub const CH_UNKNOWN_HS_ERROR: i32 = -13;
+pub const CH_FAIL_INTERNAL: i32 = -32;
+pub const CH_FLAG_CASELESS: u32 = 1;
+pub const CH_FLAG_DOTALL: u32 = 2;
+pub const CH_FLAG_MULTILINE: u32 = 4;
+pub const CH_FLAG_SINGLEMATCH: u32 = 8;
+pub const CH_FLAG_UTF8: u32 = 32;
+pub const CH_FLAG_UCP: u32 = 64;
+pub const CH_MODE_NOGROUPS: u32 = 0;
+pub const CH_MODE_GROUPS: u32 = 1048576;
+pub const CH_CALLBACK_CONTINUE: u32 = 0;
+pub const CH_CALLBACK_TERMINATE: u32 = 1;
+pub const CH_CALLBACK_SKIP_PATTERN: u32 = 2;
+pub const CH_ERROR_MATCHLIMIT: u32 = 1;
+pub const CH_ERROR_RECURSIONLIMIT: u32 = 2;
+pub const CH_CAPTURE_FLAG_INACTIVE: u32 = 0;
+pub const CH_CAPTURE_FLAG_ACTIVE: u32 = 1;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ch_database {
+    _unused: [u8; 0],
+}
+#[doc = " A Chimera pattern database.\n\n Generated by one of the Chimera compiler functions:\n  - @ref ch_compile()\n  - @ref ch_compile_multi()\n  - @ref ch_compile_ext_multi()"]
+pub type ch_database_t = ch_database;
+#[doc = " A type for errors returned by Chimera functions."]
+pub type ch_error_t = ::std::os::raw::c_int;
+extern "C" {
+    #[doc = " Free a compiled pattern database.\n\n The free callback set by @ref ch_set_database_allocator() (or @ref\n ch_set_allocator()) will be used by this function.\n\n @param db\n      A compiled pattern database. NULL may also be safely provided, in which\n      case the function does nothing.\n\n @return\n      @ref CH_SUCCESS on success, other values on failure."]
+    pub fn ch_free_database(db: *mut ch_database_t) -> ch_error_t;
+}
+extern "C" {
+    #[doc = " Utility function for identifying this release version.\n\n @return\n      A string containing the version number of this release build and the\n      date of the build. It is allocated statically, so it does not need to\n      be freed by the caller."]
+    pub fn ch_version() -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    #[doc = " Returns the size of the given database.\n\n @param database\n      Pointer to compiled expression database.\n\n @param database_size\n      On success, the size of the compiled database in bytes is placed in this\n      parameter.\n\n @return\n      @ref CH_SUCCESS on success, other values on failure."]
+    pub fn ch_database_size(
+        database: *const ch_database_t,
+        database_size: *mut usize,
+    ) -> ch_error_t;
+}
+extern "C" {
+    #[doc = " Utility function providing information about a database.\n\n @param database\n      Pointer to a compiled database.\n\n @param info\n      On success, a string containing the version and platform information for\n      the supplied database is placed in the parameter. The string is\n      allocated using the allocator supplied in @ref hs_set_allocator()\n      (or malloc() if no allocator was set) and should be freed by the caller.\n\n @return\n      @ref CH_SUCCESS on success, other values on failure."]
+    pub fn ch_database_info(
+        database: *const ch_database_t,
+        info: *mut *mut ::std::os::raw::c_char,
+    ) -> ch_error_t;
+}
+#[doc = " The type of the callback function that will be used by Chimera to allocate\n more memory at runtime as required.\n\n If Chimera is to be used in a multi-threaded, or similarly concurrent\n environment, the allocation function will need to be re-entrant, or\n similarly safe for concurrent use.\n\n @param size\n      The number of bytes to allocate.\n @return\n      A pointer to the region of memory allocated, or NULL on error."]
+pub type ch_alloc_t =
+    ::std::option::Option<unsafe extern "C" fn(size: usize) -> *mut ::std::os::raw::c_void>;
+#[doc = " The type of the callback function that will be used by Chimera to free\n memory regions previously allocated using the @ref ch_alloc_t function.\n\n @param ptr\n      The region of memory to be freed."]
+pub type ch_free_t = ::std::option::Option<unsafe extern "C" fn(ptr: *mut ::std::os::raw::c_void)>;
+extern "C" {
+    #[doc = " Set the allocate and free functions used by Chimera for allocating\n memory at runtime for stream state, scratch space, database bytecode,\n and various other data structure returned by the Chimera API.\n\n The function is equivalent to calling @ref ch_set_scratch_allocator(),\n @ref ch_set_database_allocator() and\n @ref ch_set_misc_allocator() with the provided parameters.\n\n @param alloc_func\n      A callback function pointer that allocates memory. This function must\n      return memory suitably aligned for the largest representable data type\n      on this platform.\n\n @param free_func\n      A callback function pointer that frees allocated memory.\n\n @return\n      @ref CH_SUCCESS on success, other values on failure."]
+    pub fn ch_set_allocator(alloc_func: ch_alloc_t, free_func: ch_free_t) -> ch_error_t;
+}
+extern "C" {
+    #[doc = " Set the allocate and free functions used by Chimera for allocating memory\n for database bytecode produced by the compile calls (@ref ch_compile() and @ref\n ch_compile_multi()).\n\n @param alloc_func\n      A callback function pointer that allocates memory. This function must\n      return memory suitably aligned for the largest representable data type\n      on this platform.\n\n @param free_func\n      A callback function pointer that frees allocated memory.\n\n @return\n      @ref CH_SUCCESS on success, other values on failure."]
+    pub fn ch_set_database_allocator(alloc_func: ch_alloc_t, free_func: ch_free_t) -> ch_error_t;
+}
+extern "C" {
+    #[doc = " Set the allocate and free functions used by Chimera for allocating memory\n for items returned by the Chimera API such as @ref ch_compile_error_t.\n\n @param alloc_func\n      A callback function pointer that allocates memory. This function must\n      return memory suitably aligned for the largest representable data type\n      on this platform.\n\n @param free_func\n      A callback function pointer that frees allocated memory.\n\n @return\n      @ref CH_SUCCESS on success, other values on failure."]
+    pub fn ch_set_misc_allocator(alloc_func: ch_alloc_t, free_func: ch_free_t) -> ch_error_t;
+}
+extern "C" {
+    #[doc = " Set the allocate and free functions used by Chimera for allocating memory\n for scratch space by @ref ch_alloc_scratch() and @ref ch_clone_scratch().\n\n @param alloc_func\n      A callback function pointer that allocates memory. This function must\n      return memory suitably aligned for the largest representable data type\n      on this platform.\n\n @param free_func\n      A callback function pointer that frees allocated memory.\n\n @return\n      @ref CH_SUCCESS on success, other values on failure."]
+    pub fn ch_set_scratch_allocator(alloc_func: ch_alloc_t, free_func: ch_free_t) -> ch_error_t;
+}
+#[doc = " A type containing error details that is returned by the compile calls (@ref\n ch_compile() and @ref ch_compile_multi() on failure. The caller may inspect\n the values returned in this type to determine the cause of failure."]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ch_compile_error {
+    #[doc = " A human-readable error message describing the error."]
+    pub message: *mut ::std::os::raw::c_char,
+    #[doc = " The zero-based number of the expression that caused the error (if this\n can be determined). If the error is not specific to an expression, then\n this value will be less than zero."]
+    pub expression: ::std::os::raw::c_int,
+}
+#[test]
+fn bindgen_test_layout_ch_compile_error() {
+    const UNINIT: ::std::mem::MaybeUninit<ch_compile_error> = ::std::mem::MaybeUninit::uninit();
+    let ptr = UNINIT.as_ptr();
+    assert_eq!(
+        ::std::mem::size_of::<ch_compile_error>(),
+        16usize,
+        concat!("Size of: ", stringify!(ch_compile_error))
+    );
+    assert_eq!(
+        ::std::mem::align_of::<ch_compile_error>(),
+        8usize,
+        concat!("Alignment of ", stringify!(ch_compile_error))
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).message) as usize - ptr as usize },
+        0usize,
+        concat!("Offset of field: ", stringify!(ch_compile_error), "::", stringify!(message))
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).expression) as usize - ptr as usize },
+        8usize,
+        concat!("Offset of field: ", stringify!(ch_compile_error), "::", stringify!(expression))
+    );
+}
+#[doc = " A type containing error details that is returned by the compile calls (@ref\n ch_compile() and @ref ch_compile_multi() on failure. The caller may inspect\n the values returned in this type to determine the cause of failure."]
+pub type ch_compile_error_t = ch_compile_error;
+extern "C" {
+    #[doc = " The basic regular expression compiler.\n\n This is the function call with which an expression is compiled into a\n database which can be passed to the runtime function (@ref ch_scan()).\n\n @return\n      @ref CH_SUCCESS is returned on successful compilation; @ref\n      CH_COMPILER_ERROR on failure, with details provided in the error\n      parameter."]
+    pub fn ch_compile(
+        expression: *const ::std::os::raw::c_char,
+        flags: ::std::os::raw::c_uint,
+        mode: ::std::os::raw::c_uint,
+        platform: *const hs_platform_info_t,
+        db: *mut *mut ch_database_t,
+        compile_error: *mut *mut ch_compile_error_t,
+    ) -> ch_error_t;
+}
+extern "C" {
+    #[doc = " The multiple regular expression compiler.\n\n This is the function call with which a set of expressions is compiled into a\n database which can be passed to the runtime function (@ref ch_scan()).\n Each expression can be labelled with a unique integer which is passed into\n the match callback to identify the pattern that has matched.\n\n @return\n      @ref CH_SUCCESS is returned on successful compilation; @ref\n      CH_COMPILER_ERROR on failure, with details provided in the @p error\n      parameter."]
+    pub fn ch_compile_multi(
+        expressions: *const *const ::std::os::raw::c_char,
+        flags: *const ::std::os::raw::c_uint,
+        ids: *const ::std::os::raw::c_uint,
+        elements: ::std::os::raw::c_uint,
+        mode: ::std::os::raw::c_uint,
+        platform: *const hs_platform_info_t,
+        db: *mut *mut ch_database_t,
+        compile_error: *mut *mut ch_compile_error_t,
+    ) -> ch_error_t;
+}
+extern "C" {
+    #[doc = " The multiple regular expression compiler with extended match limits support.\n\n This function is the same as @ref ch_compile_multi(), except that it also\n accepts the PCRE match limit and match recursion limit. A value of zero\n selects the default limit.\n\n @return\n      @ref CH_SUCCESS is returned on successful compilation; @ref\n      CH_COMPILER_ERROR on failure, with details provided in the @p error\n      parameter."]
+    pub fn ch_compile_ext_multi(
+        expressions: *const *const ::std::os::raw::c_char,
+        flags: *const ::std::os::raw::c_uint,
+        ids: *const ::std::os::raw::c_uint,
+        elements: ::std::os::raw::c_uint,
+        mode: ::std::os::raw::c_uint,
+        match_limit: ::std::os::raw::c_ulong,
+        match_limit_recursion: ::std::os::raw::c_ulong,
+        platform: *const hs_platform_info_t,
+        db: *mut *mut ch_database_t,
+        compile_error: *mut *mut ch_compile_error_t,
+    ) -> ch_error_t;
+}
+extern "C" {
+    #[doc = " Free an error structure generated by @ref ch_compile(), @ref\n ch_compile_multi().\n\n @param error\n      The @ref ch_compile_error_t to be freed. NULL may also be safely\n      provided.\n\n @return\n      @ref CH_SUCCESS on success, other values on failure."]
+    pub fn ch_free_compile_error(error: *mut ch_compile_error_t) -> ch_error_t;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ch_scratch {
+    _unused: [u8; 0],
+}
+#[doc = " A Chimera scratch space."]
+pub type ch_scratch_t = ch_scratch;
+#[doc = " Callback return value used to tell the Chimera matcher what to do after\n processing this match."]
+pub type ch_callback_t = ::std::os::raw::c_int;
+#[doc = " Type used to differentiate the errors raised with the @ref\n ch_error_event_handler callback."]
+pub type ch_error_event_t = ::std::os::raw::c_int;
+#[doc = " Structure representing a captured subexpression within a match. An array of\n these structures corresponding to capture groups in order is passed to the\n callback on match, with active structures identified by the\n CH_CAPTURE_FLAG_ACTIVE flag."]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ch_capture {
+    #[doc = " The flags indicating if this structure is active."]
+    pub flags: ::std::os::raw::c_uint,
+    #[doc = " offset at which this capture group begins."]
+    pub from: ::std::os::raw::c_ulonglong,
+    #[doc = " offset at which this capture group ends."]
+    pub to: ::std::os::raw::c_ulonglong,
+}
+#[test]
+fn bindgen_test_layout_ch_capture() {
+    const UNINIT: ::std::mem::MaybeUninit<ch_capture> = ::std::mem::MaybeUninit::uninit();
+    let ptr = UNINIT.as_ptr();
+    assert_eq!(
+        ::std::mem::size_of::<ch_capture>(),
+        24usize,
+        concat!("Size of: ", stringify!(ch_capture))
+    );
+    assert_eq!(
+        ::std::mem::align_of::<ch_capture>(),
+        8usize,
+        concat!("Alignment of ", stringify!(ch_capture))
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).flags) as usize - ptr as usize },
+        0usize,
+        concat!("Offset of field: ", stringify!(ch_capture), "::", stringify!(flags))
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).from) as usize - ptr as usize },
+        8usize,
+        concat!("Offset of field: ", stringify!(ch_capture), "::", stringify!(from))
+    );
+    assert_eq!(
+        unsafe { ::std::ptr::addr_of!((*ptr).to) as usize - ptr as usize },
+        16usize,
+        concat!("Offset of field: ", stringify!(ch_capture), "::", stringify!(to))
+    );
+}
+#[doc = " Structure representing a captured subexpression within a match. An array of\n these structures corresponding to capture groups in order is passed to the\n callback on match, with active structures identified by the\n CH_CAPTURE_FLAG_ACTIVE flag."]
+pub type ch_capture_t = ch_capture;
+#[doc = " Definition of the match event callback function type.\n\n A callback function matching the defined type must be provided by the\n application calling the @ref ch_scan()\n\n This callback function will be invoked whenever a match is located in the\n target data during the execution of a scan. The details of the match are\n passed in as parameters to the callback function, and the callback function\n should return a value indicating whether or not matching should continue on\n the target data. If no callbacks are desired from a scan call, NULL may be\n provided in order to suppress match production.\n\n @return\n      The callback can return @ref CH_CALLBACK_TERMINATE to stop matching.\n      Otherwise, a return value of @ref CH_CALLBACK_CONTINUE will continue,\n      with the current pattern if configured to produce multiple matches per\n      pattern, while a return value of @ref CH_CALLBACK_SKIP_PATTERN will\n      cease matching this pattern but continue matching the next pattern."]
+pub type ch_match_event_handler = ::std::option::Option<
+    unsafe extern "C" fn(
+        id: ::std::os::raw::c_uint,
+        from: ::std::os::raw::c_ulonglong,
+        to: ::std::os::raw::c_ulonglong,
+        flags: ::std::os::raw::c_uint,
+        size: ::std::os::raw::c_uint,
+        captured: *const ch_capture_t,
+        ctx: *mut ::std::os::raw::c_void,
+    ) -> ch_callback_t,
+>;
+#[doc = " Definition of the Chimera error event callback function type.\n\n A callback function matching the defined type may be provided by the\n application calling the @ref ch_scan function. This callback function\n will be invoked when an error event occurs during matching; this indicates\n that some matches for a given expression may not be reported.\n\n @return\n      The callback can return @ref CH_CALLBACK_SKIP_PATTERN to stop matching\n      for this expression, or @ref CH_CALLBACK_CONTINUE to continue scanning\n      or @ref CH_CALLBACK_TERMINATE to stop scanning."]
+pub type ch_error_event_handler = ::std::option::Option<
+    unsafe extern "C" fn(
+        error_type: ch_error_event_t,
+        id: ::std::os::raw::c_uint,
+        info: *mut ::std::os::raw::c_void,
+        ctx: *mut ::std::os::raw::c_void,
+    ) -> ch_callback_t,
+>;
+extern "C" {
+    #[doc = " The block regular expression scanner.\n\n This is the function call in which the actual pattern matching takes place\n for data provided in a single block.\n\n @return\n      Returns @ref CH_SUCCESS if the database was scanned completely, @ref\n      CH_SCAN_TERMINATED if the match callback indicated that scanning should\n      stop, or other values on error."]
+    pub fn ch_scan(
+        db: *const ch_database_t,
+        data: *const ::std::os::raw::c_char,
+        length: ::std::os::raw::c_uint,
+        flags: ::std::os::raw::c_uint,
+        scratch: *mut ch_scratch_t,
+        onEvent: ch_match_event_handler,
+        onError: ch_error_event_handler,
+        context: *mut ::std::os::raw::c_void,
+    ) -> ch_error_t;
+}
+extern "C" {
+    #[doc = " Allocate a \"scratch\" space for use by Chimera.\n\n This is required for runtime use, and one scratch space per thread, or\n concurrent caller, is required. Any allocator callback set by @ref\n ch_set_scratch_allocator() or @ref ch_set_allocator() will be used by this\n function.\n\n @return\n      @ref CH_SUCCESS on successful allocation; @ref CH_NOMEM if the\n      allocation fails.  Other errors may be returned if invalid parameters\n      are specified."]
+    pub fn ch_alloc_scratch(db: *const ch_database_t, scratch: *mut *mut ch_scratch_t)
+        -> ch_error_t;
+}
+extern "C" {
+    #[doc = " Allocate a scratch space that is a clone of an existing scratch space.\n\n This is useful when multiple concurrent threads will be using the same set\n of compiled databases, and another scratch space is required.\n\n @return\n      @ref CH_SUCCESS on success; @ref CH_NOMEM if the allocation fails.\n      Other errors may be returned if invalid parameters are specified."]
+    pub fn ch_clone_scratch(src: *const ch_scratch_t, dest: *mut *mut ch_scratch_t) -> ch_error_t;
+}
+extern "C" {
+    #[doc = " Provides the size of the given scratch space.\n\n @param scratch\n      A per-thread scratch space allocated by @ref ch_alloc_scratch() or @ref\n      ch_clone_scratch().\n\n @param scratch_size\n      On success, the size of the scratch space in bytes is placed in this\n      parameter.\n\n @return\n      @ref CH_SUCCESS on success, other values on failure."]
+    pub fn ch_scratch_size(scratch: *const ch_scratch_t, scratch_size: *mut usize) -> ch_error_t;
+}
+extern "C" {
+    #[doc = " Free a scratch block previously allocated by @ref ch_alloc_scratch() or @ref\n ch_clone_scratch().\n\n The free callback set by @ref ch_set_scratch_allocator() or @ref\n ch_set_allocator() will be used by this function.\n\n @param scratch\n      The scratch block to be freed. NULL may also be safely provided.\n\n @return\n      @ref CH_SUCCESS on success, other values on failure."]
+    pub fn ch_free_scratch(scratch: *mut ch_scratch_t) -> ch_error_t;
+}
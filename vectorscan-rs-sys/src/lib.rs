@@ -3,3 +3,6 @@
 #![allow(non_snake_case)]
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+#[cfg(feature = "chimera")]
+include!(concat!(env!("OUT_DIR"), "/chimera_bindings.rs"));
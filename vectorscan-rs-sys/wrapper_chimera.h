@@ -0,0 +1 @@
+#include <hs/ch.h>
@@ -0,0 +1,353 @@
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use super::{
+    ArchiveLimitKind, CallbackResult, Error, Scan, StreamingScanner, DEFAULT_READ_BUFFER_SIZE,
+};
+
+/// The number of bytes needed to detect an archive: a tar header's magic ends at offset 262
+const HEADER_LEN: usize = 262;
+
+// -------------------------------------------------------------------------------------------------
+// Archive Limits
+// -------------------------------------------------------------------------------------------------
+
+/// Limits on the archives walked by [`StreamingScanner::scan_archive`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ArchiveLimits {
+    /// The maximum nesting depth of archives to open; archives nested more deeply are scanned as
+    /// ordinary entries, and with a depth of 0, the input itself is scanned as is
+    pub max_depth: usize,
+
+    /// The maximum size in bytes of a single entry, after decompression
+    pub max_entry_size: u64,
+
+    /// The maximum total size in bytes of all the entries scanned
+    pub max_total_size: u64,
+
+    /// The maximum total size in bytes of the zip archives nested within other archives that are
+    /// held in memory at once
+    ///
+    /// Nested zip archives need to be seekable, so each one is read into memory while it is
+    /// walked, and the zip archives nested within it are held in memory alongside it.
+    pub max_buffered_size: u64,
+}
+
+impl Default for ArchiveLimits {
+    /// A depth of 8, entries of up to 1 GiB, up to 16 GiB in total, and up to 1 GiB of nested zip
+    /// archives in memory
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            max_entry_size: 1 << 30,
+            max_total_size: 16 << 30,
+            max_buffered_size: 1 << 30,
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// Archive Scanning
+// -------------------------------------------------------------------------------------------------
+
+impl<'db> StreamingScanner<'db> {
+    /// Scan the entries of a tar or zip archive, recursing into archives nested within it
+    ///
+    /// Each regular file in the archive is scanned as a separate stream, and nested tar and zip
+    /// archives are walked in turn, up to `limits.max_depth`. If the input is not an archive, it
+    /// is scanned as a single stream. Archives are detected by their magic bytes, not by their
+    /// names; compressed archives such as `.tar.gz` are not decompressed.
+    ///
+    /// The callback function takes the chain of entry paths leading to the entry being scanned,
+    /// starting with the path within the outermost archive, followed by the arguments described
+    /// in [`StreamingScanner::scan_buf_reader`]. Match offsets are relative to the start of the
+    /// entry. If the callback function stops the scan, the whole walk stops.
+    ///
+    /// Zip entries that cannot be opened, because they are encrypted or use an unsupported
+    /// compression method, are skipped, and their paths are returned in `ArchiveScan::skipped`.
+    ///
+    /// If an entry or the entries in total exceed the size limits, `Error::ArchiveLimit` is
+    /// returned, saying which limit was exceeded and where. Exceeding `limits.max_depth` is not an
+    /// error. Errors reading the input or malformed archives are returned as `Error::Io`.
+    ///
+    /// Returns the outcome of the walk, as [`StreamScanner::scan`](crate::StreamScanner::scan)
+    /// would return it, along with the total number of bytes scanned and the skipped entries.
+    pub fn scan_archive<T, F, R>(
+        &mut self,
+        reader: T,
+        limits: ArchiveLimits,
+        on_match: F,
+    ) -> ArchiveScan<R::Output>
+    where
+        T: Read + Seek,
+        F: FnMut(&[PathBuf], u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        let mut walker = Walker {
+            scanner: self,
+            limits,
+            on_match,
+            stopped: None,
+            path: Vec::new(),
+            buffer: vec![0; DEFAULT_READ_BUFFER_SIZE],
+            total: 0,
+            buffered: 0,
+            skipped: Vec::new(),
+            terminated: false,
+        };
+        let res = walker.walk(reader).map(|()| {
+            if walker.terminated {
+                Scan::Terminate
+            } else {
+                Scan::Continue
+            }
+        });
+        ArchiveScan {
+            bytes: walker.total,
+            skipped: walker.skipped,
+            outcome: R::into_output(walker.stopped, res),
+        }
+    }
+}
+
+/// The result of walking an archive with [`StreamingScanner::scan_archive`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveScan<T> {
+    /// The total number of bytes scanned
+    pub bytes: u64,
+
+    /// The chains of entry paths of the zip entries that were skipped because they could not be
+    /// opened, such as encrypted entries
+    pub skipped: Vec<Vec<PathBuf>>,
+
+    /// The outcome of the walk
+    pub outcome: T,
+}
+
+impl<T, E> ArchiveScan<Result<T, E>> {
+    /// Convert a walk with a `Result` outcome into a `Result` of a walk, for use with `?`
+    pub fn transpose(self) -> Result<ArchiveScan<T>, E> {
+        Ok(ArchiveScan {
+            bytes: self.bytes,
+            skipped: self.skipped,
+            outcome: self.outcome?,
+        })
+    }
+}
+
+/// The archive formats that are walked
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ArchiveKind {
+    Tar,
+    Zip,
+}
+
+impl ArchiveKind {
+    /// Detect the archive format of an input from its first `HEADER_LEN` bytes
+    fn detect(header: &[u8]) -> Option<Self> {
+        if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Some(Self::Zip)
+        } else if header.get(257..262) == Some(b"ustar") {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// Read up to `HEADER_LEN` bytes from the start of the input
+fn read_header(reader: &mut dyn Read) -> io::Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    reader.take(HEADER_LEN as u64).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+/// The state of a walk through nested archives
+struct Walker<'s, 'db, F, R> {
+    scanner: &'s mut StreamingScanner<'db>,
+    limits: ArchiveLimits,
+    on_match: F,
+    /// The value returned by the callback function that stopped the walk, if any
+    stopped: Option<R>,
+    /// The chain of entry paths leading to the current entry
+    path: Vec<PathBuf>,
+    buffer: Vec<u8>,
+    /// The total number of bytes scanned so far
+    total: u64,
+    /// The total size of the nested zip archives currently held in memory
+    buffered: u64,
+    /// The entries that could not be opened
+    skipped: Vec<Vec<PathBuf>>,
+    /// Whether the callback function has stopped the walk
+    terminated: bool,
+}
+
+impl<'s, 'db, F, R> Walker<'s, 'db, F, R>
+where
+    F: FnMut(&[PathBuf], u32, u64, u64, u32) -> R,
+    R: CallbackResult,
+{
+    /// Walk the input, if it is an archive, or else scan it as a single stream
+    fn walk<T: Read + Seek>(&mut self, mut reader: T) -> Result<(), Error> {
+        let start = reader.stream_position()?;
+        let header = read_header(&mut reader)?;
+        reader.seek(SeekFrom::Start(start))?;
+        match ArchiveKind::detect(&header) {
+            Some(ArchiveKind::Zip) if self.limits.max_depth > 0 => self.scan_zip(reader, 1),
+            Some(ArchiveKind::Tar) if self.limits.max_depth > 0 => self.scan_tar(&mut reader, 1),
+            _ => self.scan_stream(&mut reader),
+        }
+    }
+
+    /// Scan an entry whose archives are at the given depth, opening it if it is an archive
+    fn scan_entry(&mut self, reader: &mut dyn Read, depth: usize) -> Result<(), Error> {
+        let header = read_header(reader)?;
+        let kind = ArchiveKind::detect(&header);
+        let mut reader = Cursor::new(header).chain(reader);
+        match kind {
+            Some(ArchiveKind::Zip) if depth < self.limits.max_depth => {
+                // Zip archives need to be seekable, so are read into memory
+                let limits = self.limits;
+                let available = limits.max_buffered_size - self.buffered;
+                let (max_size, kind, limit) = if available < limits.max_entry_size {
+                    let kind = ArchiveLimitKind::BufferedSize;
+                    (available, kind, limits.max_buffered_size)
+                } else {
+                    let kind = ArchiveLimitKind::EntrySize;
+                    (limits.max_entry_size, kind, limits.max_entry_size)
+                };
+                let mut data = Vec::new();
+                (&mut reader).take(max_size + 1).read_to_end(&mut data)?;
+                if data.len() as u64 > max_size {
+                    return Err(limit_error(&self.path, kind, limit));
+                }
+                let size = data.len() as u64;
+                self.buffered += size;
+                let res = self.scan_zip(Cursor::new(data), depth + 1);
+                self.buffered -= size;
+                res
+            }
+            Some(ArchiveKind::Tar) if depth < self.limits.max_depth => {
+                self.scan_tar(&mut reader, depth + 1)
+            }
+            _ => self.scan_stream(&mut reader),
+        }
+    }
+
+    /// Scan the regular files in a tar archive
+    fn scan_tar(&mut self, reader: &mut dyn Read, depth: usize) -> Result<(), Error> {
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            self.path.push(entry.path()?.into_owned());
+            let res = self.scan_entry(&mut entry, depth);
+            self.path.pop();
+            res?;
+            if self.terminated {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Scan the regular files in a zip archive
+    fn scan_zip<T: Read + Seek>(&mut self, reader: T, depth: usize) -> Result<(), Error> {
+        let mut archive = zip::ZipArchive::new(reader).map_err(io::Error::from)?;
+        for index in 0..archive.len() {
+            let name = PathBuf::from(archive.name_for_index(index).unwrap_or_default());
+            let mut file = match archive.by_index(index) {
+                Ok(file) => file,
+                // Entries that are encrypted or use an unsupported compression method are skipped
+                Err(zip::result::ZipError::UnsupportedArchive(_)) => {
+                    let mut path = self.path.clone();
+                    path.push(name);
+                    self.skipped.push(path);
+                    continue;
+                }
+                Err(e) => return Err(io::Error::from(e).into()),
+            };
+            if !file.is_file() {
+                continue;
+            }
+            self.path.push(name);
+            let res = self.scan_entry(&mut file, depth);
+            self.path.pop();
+            res?;
+            if self.terminated {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Scan an entry as a single stream
+    fn scan_stream(&mut self, reader: &mut dyn Read) -> Result<(), Error> {
+        let Self {
+            scanner,
+            limits,
+            on_match,
+            stopped,
+            path,
+            buffer,
+            total,
+            terminated,
+            ..
+        } = self;
+        let mut on_match = |id, from, to, flags| {
+            let res = on_match(path, id, from, to, flags);
+            if res.should_continue() {
+                Scan::Continue
+            } else {
+                *stopped = Some(res);
+                Scan::Terminate
+            }
+        };
+
+        let mut stream = scanner.open_stream()?;
+        let mut size = 0;
+        loop {
+            let length = match reader.read(buffer) {
+                Ok(0) => break,
+                Ok(length) => length,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            size += length as u64;
+            *total += length as u64;
+            if size > limits.max_entry_size {
+                return Err(limit_error(
+                    path,
+                    ArchiveLimitKind::EntrySize,
+                    limits.max_entry_size,
+                ));
+            }
+            if *total > limits.max_total_size {
+                return Err(limit_error(
+                    path,
+                    ArchiveLimitKind::TotalSize,
+                    limits.max_total_size,
+                ));
+            }
+            if stream.scan(&buffer[..length], &mut on_match)? == Scan::Terminate {
+                *terminated = true;
+                return Ok(());
+            }
+        }
+        if stream.close_with(&mut on_match)? == Scan::Terminate {
+            *terminated = true;
+        }
+        Ok(())
+    }
+}
+
+/// Report an exceeded size limit at the given entry
+fn limit_error(path: &[PathBuf], kind: ArchiveLimitKind, limit: u64) -> Error {
+    Error::ArchiveLimit {
+        kind,
+        limit,
+        path: path.to_vec(),
+    }
+}
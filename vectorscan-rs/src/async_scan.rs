@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use super::{
+    BlockDatabase, Error, Match, OwnedStreamScanner, Pattern, Scan, StreamingDatabase,
+    DEFAULT_READ_BUFFER_SIZE,
+};
+
+// -------------------------------------------------------------------------------------------------
+// Async Compilation
+// -------------------------------------------------------------------------------------------------
+
+impl BlockDatabase {
+    /// Create a new database with the given patterns, compiling it on tokio's blocking thread
+    /// pool using `spawn_blocking`
+    ///
+    /// # Panics
+    /// If called outside of a tokio runtime.
+    pub async fn new_async(patterns: Vec<Pattern>) -> Result<Self, Error> {
+        compile_blocking(move || Self::new(patterns)).await
+    }
+}
+
+impl StreamingDatabase {
+    /// Create a new database with the given patterns, compiling it on tokio's blocking thread
+    /// pool using `spawn_blocking`
+    ///
+    /// # Panics
+    /// If called outside of a tokio runtime.
+    pub async fn new_async(patterns: Vec<Pattern>) -> Result<Self, Error> {
+        compile_blocking(move || Self::new(patterns)).await
+    }
+}
+
+/// Run the given compilation function with `spawn_blocking`, resuming any panic it raises
+async fn compile_blocking<T, F>(compile: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, Error> + Send + 'static,
+{
+    match tokio::task::spawn_blocking(compile).await {
+        Ok(res) => res,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        // The task was cancelled because the runtime is shutting down
+        Err(e) => Err(Error::Io(e.into())),
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// Async Match Streams
+// -------------------------------------------------------------------------------------------------
+
+/// The scanning state shared by the async match streams
+#[derive(Debug)]
+struct MatchState {
+    /// The stream being scanned, or `None` once it has been closed or has failed
+    stream: Option<OwnedStreamScanner>,
+    /// Matches that have been found but not yet yielded
+    pending: VecDeque<Match>,
+}
+
+impl MatchState {
+    fn new(stream: OwnedStreamScanner) -> Self {
+        Self {
+            stream: Some(stream),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Yield the next pending match, or the end of the matches once the stream has been closed
+    fn poll_pending(&mut self) -> Option<Poll<Option<Result<Match, Error>>>> {
+        match self.pending.pop_front() {
+            Some(m) => Some(Poll::Ready(Some(Ok(m)))),
+            None if self.stream.is_none() => Some(Poll::Ready(None)),
+            None => None,
+        }
+    }
+
+    /// Scan the given data, or close the stream if it is empty, queueing any matches
+    fn scan(&mut self, data: &[u8]) -> Result<(), Error> {
+        let Some(stream) = self.stream.as_mut() else {
+            return Ok(());
+        };
+        if data.is_empty() {
+            return self.close();
+        }
+        let pending = &mut self.pending;
+        stream.scan(data, |id, from, to, _flags| {
+            pending.push_back(Match::new(id, from, to));
+            Scan::Continue
+        })?;
+        Ok(())
+    }
+
+    /// Close the stream, queueing any matches at the end of the stream
+    fn close(&mut self) -> Result<(), Error> {
+        if let Some(stream) = self.stream.take() {
+            self.pending.extend(stream.close_find_all()?);
+        }
+        Ok(())
+    }
+
+    /// End the matches with the given error, discarding the stream
+    fn fail(&mut self, err: Error) -> Poll<Option<Result<Match, Error>>> {
+        self.stream = None;
+        self.pending.clear();
+        Poll::Ready(Some(Err(err)))
+    }
+}
+
+/// An async stream of the matches found by scanning everything read from a tokio `AsyncRead`
+///
+/// The reader is scanned through an [`OwnedStreamScanner`] as it is read, and the stream is
+/// closed at the end of the input, so that matches at the end of the stream are reported. After
+/// an error, no more matches are yielded.
+#[derive(Debug)]
+pub struct ReaderMatches<R> {
+    reader: R,
+    buffer: Box<[u8]>,
+    state: MatchState,
+}
+
+impl<R: AsyncRead + Unpin> ReaderMatches<R> {
+    /// Scan the given reader through the given stream, using a buffer of
+    /// `DEFAULT_READ_BUFFER_SIZE` bytes
+    pub fn new(stream: OwnedStreamScanner, reader: R) -> Self {
+        Self::with_buffer_size(stream, reader, DEFAULT_READ_BUFFER_SIZE)
+    }
+
+    /// Scan the given reader through the given stream, using a buffer of the given size
+    pub fn with_buffer_size(stream: OwnedStreamScanner, reader: R, buffer_size: usize) -> Self {
+        Self {
+            reader,
+            buffer: vec![0; buffer_size.max(1)].into_boxed_slice(),
+            state: MatchState::new(stream),
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for ReaderMatches<R> {
+    type Item = Result<Match, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(poll) = this.state.poll_pending() {
+                return poll;
+            }
+            let mut buf = ReadBuf::new(&mut this.buffer);
+            if let Err(e) = ready!(Pin::new(&mut this.reader).poll_read(cx, &mut buf)) {
+                return this.state.fail(e.into());
+            }
+            if let Err(e) = this.state.scan(buf.filled()) {
+                return this.state.fail(e);
+            }
+        }
+    }
+}
+
+/// An async stream of the matches found by scanning the chunks of bytes yielded by an async
+/// stream, such as a `Stream<Item = Bytes>`
+///
+/// The chunks are scanned through an [`OwnedStreamScanner`] as they arrive, and the stream is
+/// closed once the chunks end, so that matches at the end of the stream are reported. After an
+/// error, no more matches are yielded.
+#[derive(Debug)]
+pub struct ChunkMatches<S> {
+    chunks: S,
+    state: MatchState,
+}
+
+impl<S, B> ChunkMatches<S>
+where
+    S: Stream<Item = B> + Unpin,
+    B: AsRef<[u8]>,
+{
+    /// Scan the given chunks through the given stream
+    pub fn new(stream: OwnedStreamScanner, chunks: S) -> Self {
+        Self {
+            chunks,
+            state: MatchState::new(stream),
+        }
+    }
+}
+
+impl<S, B> Stream for ChunkMatches<S>
+where
+    S: Stream<Item = B> + Unpin,
+    B: AsRef<[u8]>,
+{
+    type Item = Result<Match, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(poll) = this.state.poll_pending() {
+                return poll;
+            }
+            let res = match ready!(Pin::new(&mut this.chunks).poll_next(cx)) {
+                // Empty chunks are skipped, as an empty scan closes the stream
+                Some(chunk) if chunk.as_ref().is_empty() => continue,
+                Some(chunk) => this.state.scan(chunk.as_ref()),
+                None => this.state.close(),
+            };
+            if let Err(e) = res {
+                return this.state.fail(e);
+            }
+        }
+    }
+}
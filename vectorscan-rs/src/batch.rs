@@ -0,0 +1,222 @@
+use rayon::prelude::*;
+
+use super::{BlockDatabase, Error, Flag, Match, Scan, Scratch, Stream};
+
+// -------------------------------------------------------------------------------------------------
+// Parallel Batch Scanning
+// -------------------------------------------------------------------------------------------------
+
+impl BlockDatabase {
+    /// Scan each of the inputs in parallel using rayon, returning the matches for each input
+    ///
+    /// The results are in the same order as the inputs. Each rayon worker thread uses its own
+    /// scratch space, as with [`BlockDatabase::scan`].
+    pub fn scan_batch<I>(&self, inputs: &[I]) -> Result<Vec<Vec<Match>>, Error>
+    where
+        I: AsRef<[u8]> + Sync,
+    {
+        self.fold_batch(inputs, Vec::new, |mut matches, m| {
+            matches.push(m);
+            matches
+        })
+    }
+
+    /// Scan each of the inputs in parallel using rayon, folding the matches of each input into
+    /// a value
+    ///
+    /// For each input, the fold starts from a value produced by `identity`, and `fold` is called
+    /// with the accumulated value and each match in turn. The results are in the same order as
+    /// the inputs.
+    pub fn fold_batch<I, T, ID, F>(
+        &self,
+        inputs: &[I],
+        identity: ID,
+        fold: F,
+    ) -> Result<Vec<T>, Error>
+    where
+        I: AsRef<[u8]> + Sync,
+        T: Send,
+        ID: Fn() -> T + Sync,
+        F: Fn(T, Match) -> T + Sync,
+    {
+        inputs
+            .par_iter()
+            .map(|input| {
+                let mut acc = Some(identity());
+                self.scan(input.as_ref(), |id, from, to, _flags| {
+                    let prev = acc.take().expect("accumulator is present between matches");
+                    acc = Some(fold(prev, Match::new(id, from, to)));
+                    Scan::Continue
+                })?;
+                Ok(acc.expect("accumulator is present after scanning"))
+            })
+            .collect()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// Parallel Chunked Scanning
+// -------------------------------------------------------------------------------------------------
+
+/// The number of bytes after a chunk that are scanned along with it, so that assertions such as
+/// `\b` and `$` at the end of a match see the same data as in a scan of the entire input
+const RIGHT_CONTEXT: usize = 2;
+
+/// The number of bytes scanned along with a chunk on either side, beyond the maximum match width,
+/// when a pattern uses `Flag::UTF8` or `Flag::UCP`: assertions such as `\b` then look at a whole
+/// character, which takes up to 4 bytes
+const UNICODE_CONTEXT: usize = 4;
+
+/// The smallest chunk size chosen by `BlockDatabase::find_all_parallel`
+const MIN_CHUNK_SIZE: usize = 1 << 20;
+
+impl BlockDatabase {
+    /// Scan a single large input in parallel using rayon, returning all of the matches
+    ///
+    /// The input is split into chunks that are scanned on rayon worker threads. The chunk size is
+    /// chosen based on the size of the input and the number of threads; use
+    /// `find_all_parallel_with_chunk_size` to choose it explicitly.
+    ///
+    /// See [`BlockDatabase::find_all_parallel_with_chunk_size`] for details.
+    pub fn find_all_parallel(&self, data: &[u8]) -> Result<Vec<Match>, Error> {
+        let chunk_size = (data.len() / rayon::current_num_threads()).max(MIN_CHUNK_SIZE);
+        self.find_all_parallel_with_chunk_size(data, chunk_size)
+    }
+
+    /// Scan a single large input in parallel using rayon, splitting it into chunks of the given
+    /// size, and returning all of the matches
+    ///
+    /// Each chunk is scanned along with the preceding bytes that could be part of a match ending
+    /// within it, as determined by the maximum match width of each pattern from
+    /// `hs_expression_info`. Only matches that end within a chunk are kept from the scan of that
+    /// chunk, so the matches are exactly those reported by scanning the entire input at once.
+    /// Matches are returned in order of chunk, and in the order reported within each chunk.
+    ///
+    /// Chunked scanning is not possible when a pattern has unbounded width, or uses
+    /// `Flag::SINGLEMATCH` or `Flag::COMBINATION`. In that case, the input is scanned on the
+    /// current thread instead: in block mode if possible, and otherwise in streaming mode, using
+    /// a streaming database compiled from the same patterns.
+    ///
+    /// The patterns are analyzed on the first call, and the streaming database is compiled the
+    /// first time it is needed; both are reused by later calls with this database and its clones.
+    pub fn find_all_parallel_with_chunk_size(
+        &self,
+        data: &[u8],
+        chunk_size: usize,
+    ) -> Result<Vec<Match>, Error> {
+        let Some(max_width) = self.max_match_width() else {
+            return self.find_all_sequential(data);
+        };
+
+        let unicode = self
+            .patterns()
+            .iter()
+            .any(|p| p.flags().intersects(Flag::UTF8 | Flag::UCP));
+        let (left_context, right_context) = if unicode {
+            (max_width as usize + UNICODE_CONTEXT, UNICODE_CONTEXT)
+        } else {
+            (max_width as usize + 1, RIGHT_CONTEXT)
+        };
+
+        // Each chunk and its context must fit into a single call to `hs_scan`
+        let max_chunk_size = (u32::MAX as usize).saturating_sub(left_context + right_context);
+        if max_chunk_size == 0 {
+            return self.find_all_sequential(data);
+        }
+        let chunk_size = chunk_size.clamp(1, max_chunk_size);
+
+        let num_chunks = data.len().div_ceil(chunk_size).max(1);
+        let chunks: Vec<Vec<Match>> = (0..num_chunks)
+            .into_par_iter()
+            .map(|i| {
+                let start = i * chunk_size;
+                let end = (start + chunk_size).min(data.len());
+                let mut window_start = start.saturating_sub(left_context);
+                let mut window_end = (end + right_context).min(data.len());
+                if unicode {
+                    // Don't split characters at the edges of the window
+                    window_start = floor_char_boundary(data, window_start);
+                    window_end = ceil_char_boundary(data, window_end);
+                }
+
+                // A match is kept by the chunk in which it ends; a match ending at the very
+                // start of the input belongs to the first chunk
+                let owns = |to: u64| (to > start as u64 || i == 0) && to <= end as u64;
+
+                let mut matches = Vec::new();
+                self.scan(&data[window_start..window_end], |id, from, to, _flags| {
+                    let (from, to) = (from + window_start as u64, to + window_start as u64);
+                    if owns(to) {
+                        matches.push(Match::new(id, from, to));
+                    }
+                    Scan::Continue
+                })?;
+                Ok(matches)
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(chunks.into_iter().flatten().collect())
+    }
+
+    /// Get the maximum width of a match of any pattern, if every pattern can be scanned in
+    /// overlapping chunks
+    ///
+    /// This is computed using `hs_expression_info` on first use, and shared by clones of this
+    /// database.
+    fn max_match_width(&self) -> Option<u32> {
+        *self.derived().max_match_width.get_or_init(|| {
+            self.patterns().iter().try_fold(0, |width, pattern| {
+                if pattern
+                    .flags()
+                    .intersects(Flag::SINGLEMATCH | Flag::COMBINATION)
+                {
+                    return None;
+                }
+                let info = pattern.info().ok()?;
+                Some(width.max(info.max_width?))
+            })
+        })
+    }
+
+    /// Scan the input on the current thread, in block mode if possible, or else streaming mode
+    fn find_all_sequential(&self, data: &[u8]) -> Result<Vec<Match>, Error> {
+        if u32::try_from(data.len()).is_ok() {
+            return self.find_all(data);
+        }
+
+        let db = self.streaming_twin()?;
+        let mut scratch = Scratch::new(&db)?;
+        let mut stream = Stream::new(&db)?;
+        let mut matches = stream.find_all(&mut scratch, data)?;
+        matches.extend(stream.close_find_all(&mut scratch)?);
+        Ok(matches)
+    }
+}
+
+/// Move an offset back to the start of the UTF-8 character containing it, looking back at most 3
+/// bytes
+fn floor_char_boundary(data: &[u8], mut offset: usize) -> usize {
+    for _ in 1..UNICODE_CONTEXT {
+        match data.get(offset) {
+            Some(b) if offset > 0 && is_continuation_byte(*b) => offset -= 1,
+            _ => break,
+        }
+    }
+    offset
+}
+
+/// Move an offset forward to the end of the UTF-8 character containing it, looking ahead at most
+/// 3 bytes
+fn ceil_char_boundary(data: &[u8], mut offset: usize) -> usize {
+    for _ in 1..UNICODE_CONTEXT {
+        match data.get(offset) {
+            Some(b) if is_continuation_byte(*b) => offset += 1,
+            _ => break,
+        }
+    }
+    offset
+}
+
+fn is_continuation_byte(b: u8) -> bool {
+    b & 0xC0 == 0x80
+}
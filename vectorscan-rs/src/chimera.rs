@@ -0,0 +1,555 @@
+//! Bindings to Chimera, the hybrid of Vectorscan and PCRE.
+//!
+//! Chimera supports the full PCRE syntax, and can report the offsets of capture groups.
+//! For more detail, see the [Chimera documentation](https://intel.github.io/hyperscan/dev-reference/chimera.html).
+
+use crate::error::{report_free_failure, AsResult, Error};
+use bitflags::bitflags;
+use foreign_types::{foreign_type, ForeignType};
+use std::any::Any;
+use std::ffi::{c_int, c_uint, c_ulonglong, c_void, CString};
+use std::mem::MaybeUninit;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use vectorscan_rs_sys as hs;
+
+use super::{CallbackResult, HyperscanErrorCode, Scan};
+
+foreign_type! {
+    #[derive(Debug)]
+    unsafe type ChimeraCompileError: Send + Sync {
+        type CType = hs::ch_compile_error_t;
+        fn drop = compile_error_drop;
+    }
+
+    #[derive(Debug)]
+    unsafe type ChimeraRawDatabase: Send + Sync {
+        type CType = hs::ch_database_t;
+        fn drop = database_drop;
+    }
+
+    /// A scratch space for Chimera scanning
+    #[derive(Debug)]
+    pub(crate) unsafe type ChimeraScratch: Send + Sync {
+        type CType = hs::ch_scratch_t;
+        fn drop = scratch_drop;
+    }
+}
+
+unsafe fn database_drop(v: *mut hs::ch_database_t) {
+    let res = hs::ch_free_database(v);
+    if res != hs::CH_SUCCESS as hs::ch_error_t {
+        report_free_failure("ch_free_database", res);
+    }
+}
+
+unsafe fn scratch_drop(v: *mut hs::ch_scratch_t) {
+    let res = hs::ch_free_scratch(v);
+    if res != hs::CH_SUCCESS as hs::ch_error_t {
+        report_free_failure("ch_free_scratch", res);
+    }
+}
+
+unsafe fn compile_error_drop(v: *mut hs::ch_compile_error_t) {
+    let res = hs::ch_free_compile_error(v);
+    if res != hs::CH_SUCCESS as hs::ch_error_t {
+        report_free_failure("ch_free_compile_error", res);
+    }
+}
+
+impl ChimeraCompileError {
+    fn message(&self) -> String {
+        unsafe {
+            let err = self.0.as_ptr();
+
+            std::ffi::CStr::from_ptr((*err).message)
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+    fn expression(&self) -> i32 {
+        unsafe { (*self.0.as_ptr()).expression }
+    }
+}
+
+bitflags! {
+    /// Flags that modify the behavior of a Chimera pattern
+    #[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct ChimeraFlag: u32 {
+        const CASELESS = hs::CH_FLAG_CASELESS;
+        const DOTALL = hs::CH_FLAG_DOTALL;
+        const MULTILINE = hs::CH_FLAG_MULTILINE;
+        const SINGLEMATCH = hs::CH_FLAG_SINGLEMATCH;
+        const UTF8 = hs::CH_FLAG_UTF8;
+        const UCP = hs::CH_FLAG_UCP;
+    }
+}
+
+bitflags! {
+    /// The compilation mode of a Chimera database
+    #[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct ChimeraMode: u32 {
+        /// Only report the overall match offsets
+        const NOGROUPS = hs::CH_MODE_NOGROUPS;
+        /// Additionally report the offsets of capture groups
+        const GROUPS = hs::CH_MODE_GROUPS;
+    }
+}
+
+/// A pattern to be compiled into a [`ChimeraDatabase`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ChimeraPattern {
+    expression: Vec<u8>,
+    flags: ChimeraFlag,
+    id: Option<u32>,
+}
+
+impl ChimeraPattern {
+    pub fn new(expression: Vec<u8>, flags: ChimeraFlag, id: Option<u32>) -> Self {
+        Self {
+            expression,
+            flags,
+            id,
+        }
+    }
+}
+
+/// Limits on the PCRE matching done by Chimera
+///
+/// A limit of 0 uses the default from Chimera.
+/// When a limit is exceeded during a scan, the error callback is invoked.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ChimeraMatchLimits {
+    /// The PCRE `match_limit` setting
+    pub match_limit: u64,
+    /// The PCRE `match_limit_recursion` setting
+    pub match_limit_recursion: u64,
+}
+
+// -------------------------------------------------------------------------------------------------
+// Chimera Database
+// -------------------------------------------------------------------------------------------------
+
+/// A database that supports Chimera's block-based matching APIs
+#[derive(Debug)]
+pub struct ChimeraDatabase {
+    inner: ChimeraRawDatabase,
+}
+
+impl ChimeraDatabase {
+    /// Create a new database with the given patterns, using the default match limits
+    pub fn new(patterns: Vec<ChimeraPattern>, mode: ChimeraMode) -> Result<Self, Error> {
+        Self::with_match_limits(patterns, mode, ChimeraMatchLimits::default())
+    }
+
+    /// Create a new database with the given patterns and match limits
+    pub fn with_match_limits(
+        patterns: Vec<ChimeraPattern>,
+        mode: ChimeraMode,
+        limits: ChimeraMatchLimits,
+    ) -> Result<Self, Error> {
+        let mut c_exprs = Vec::with_capacity(patterns.len());
+        let mut c_flags = Vec::with_capacity(patterns.len());
+        let mut c_ids = Vec::with_capacity(patterns.len());
+        for ChimeraPattern {
+            expression,
+            flags,
+            id,
+        } in patterns
+        {
+            c_exprs.push(CString::new(expression)?);
+            c_flags.push(flags.bits());
+            c_ids.push(id.unwrap_or(0));
+        }
+
+        let mut db = MaybeUninit::zeroed();
+        let mut err = MaybeUninit::zeroed();
+        unsafe {
+            hs::ch_compile_ext_multi(
+                c_exprs
+                    .iter()
+                    .map(|expr| expr.as_ptr())
+                    .collect::<Vec<_>>()
+                    .as_ptr(),
+                c_flags.as_ptr(),
+                c_ids.as_ptr(),
+                c_exprs.len() as u32,
+                mode.bits(),
+                limits.match_limit as _,
+                limits.match_limit_recursion as _,
+                ptr::null(),
+                db.as_mut_ptr(),
+                err.as_mut_ptr(),
+            )
+            .ok()
+            .map_err(|_e| {
+                // The details of error value `_e` are stored in `err`; convert that and ignore `_e`
+                let err = ChimeraCompileError::from_ptr(err.assume_init());
+                Error::HyperscanCompile(err.message(), err.expression())
+            })?;
+            Ok(Self {
+                inner: ChimeraRawDatabase::from_ptr(db.assume_init()),
+            })
+        }
+    }
+
+    /// Create a new scanner from this database
+    pub fn create_scanner(&self) -> Result<ChimeraScanner<'_>, Error> {
+        ChimeraScanner::new(self)
+    }
+
+    /// Get the size in bytes of the database
+    pub fn size(&self) -> Result<usize, Error> {
+        let mut database_size = MaybeUninit::zeroed();
+        unsafe {
+            hs::ch_database_size(self.inner.as_ptr(), database_size.as_mut_ptr())
+                .ok()
+                .map(|()| database_size.assume_init())
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// Chimera Scratch
+// -------------------------------------------------------------------------------------------------
+
+impl ChimeraScratch {
+    fn new(database: &ChimeraDatabase) -> Result<Self, Error> {
+        let mut scratch = MaybeUninit::zeroed();
+        unsafe {
+            hs::ch_alloc_scratch(database.inner.as_ptr(), scratch.as_mut_ptr())
+                .ok()
+                .map(|()| ChimeraScratch::from_ptr(scratch.assume_init()))
+        }
+    }
+
+    /// Creates a copy of the scratch space using `ch_clone_scratch`.
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        let mut scratch = MaybeUninit::zeroed();
+        unsafe {
+            hs::ch_clone_scratch(self.0.as_ptr(), scratch.as_mut_ptr())
+                .ok()
+                .map(|()| ChimeraScratch::from_ptr(scratch.assume_init()))
+        }
+    }
+}
+
+/// Creates a copy of the scratch space using `ch_clone_scratch`.
+///
+/// Panics if the copy fails; use `ChimeraScratch::try_clone` to handle that case.
+impl Clone for ChimeraScratch {
+    fn clone(&self) -> Self {
+        self.try_clone().expect("ch_clone_scratch failed")
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// Chimera Scan Callbacks
+// -------------------------------------------------------------------------------------------------
+
+/// The result returned by a Chimera scan callback
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChimeraScan {
+    /// Continue scanning
+    Continue,
+    /// Stop scanning entirely
+    Terminate,
+    /// Stop reporting matches for the current pattern, but continue scanning for other patterns
+    SkipPattern,
+}
+
+impl ChimeraScan {
+    fn as_callback(&self) -> hs::ch_callback_t {
+        match self {
+            ChimeraScan::Continue => hs::CH_CALLBACK_CONTINUE as hs::ch_callback_t,
+            ChimeraScan::Terminate => hs::CH_CALLBACK_TERMINATE as hs::ch_callback_t,
+            ChimeraScan::SkipPattern => hs::CH_CALLBACK_SKIP_PATTERN as hs::ch_callback_t,
+        }
+    }
+}
+
+/// A value that can be returned by a Chimera scan callback
+///
+/// This is implemented for `ChimeraScan`, for which the scan returns `Result<Scan, Error>`, and
+/// for every [`CallbackResult`], which determines what the scan returns as it does for
+/// [`BlockScanner::scan`](crate::BlockScanner::scan).
+pub trait ChimeraCallbackResult: Sized {
+    /// The type returned by a scan whose callbacks return this type
+    type Output;
+
+    /// How should Chimera proceed after the callback has returned this value?
+    fn action(&self) -> ChimeraScan;
+
+    /// Produce the result of a scan
+    ///
+    /// `stopped` is the value returned by the callback that stopped the scan, if any.
+    /// `res` is the result of the underlying Chimera call.
+    fn into_output(stopped: Option<Self>, res: Result<Scan, Error>) -> Self::Output;
+}
+
+impl ChimeraCallbackResult for ChimeraScan {
+    type Output = Result<Scan, Error>;
+
+    fn action(&self) -> ChimeraScan {
+        *self
+    }
+
+    fn into_output(_stopped: Option<Self>, res: Result<Scan, Error>) -> Self::Output {
+        res
+    }
+}
+
+impl<R: CallbackResult> ChimeraCallbackResult for R {
+    type Output = R::Output;
+
+    fn action(&self) -> ChimeraScan {
+        if self.should_continue() {
+            ChimeraScan::Continue
+        } else {
+            ChimeraScan::Terminate
+        }
+    }
+
+    fn into_output(stopped: Option<Self>, res: Result<Scan, Error>) -> Self::Output {
+        R::into_output(stopped, res)
+    }
+}
+
+/// A capture group reported by a Chimera match
+///
+/// The capture at index 0 corresponds to the entire match.
+/// Captures for groups that did not participate in the match are inactive.
+#[repr(transparent)]
+pub struct Capture(hs::ch_capture_t);
+
+impl Capture {
+    /// Did this capture group participate in the match?
+    pub fn is_active(&self) -> bool {
+        self.0.flags & hs::CH_CAPTURE_FLAG_ACTIVE != 0
+    }
+
+    /// The offsets of the capture group, if it participated in the match
+    pub fn range(&self) -> Option<Range<u64>> {
+        self.is_active().then_some(self.0.from..self.0.to)
+    }
+}
+
+impl std::fmt::Debug for Capture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Capture").field(&self.range()).finish()
+    }
+}
+
+/// An error event reported during a Chimera scan
+///
+/// When one of these is reported, some matches for the given pattern may not be reported.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ChimeraErrorEvent {
+    /// PCRE exceeded its match limit
+    MatchLimit,
+    /// PCRE exceeded its recursion limit
+    RecursionLimit,
+    /// An error event that is not known to these bindings
+    Unknown(i32),
+}
+
+impl From<hs::ch_error_event_t> for ChimeraErrorEvent {
+    fn from(event: hs::ch_error_event_t) -> Self {
+        match event as u32 {
+            hs::CH_ERROR_MATCHLIMIT => Self::MatchLimit,
+            hs::CH_ERROR_RECURSIONLIMIT => Self::RecursionLimit,
+            _ => Self::Unknown(event),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// Chimera Scanner
+// -------------------------------------------------------------------------------------------------
+
+/// A scanner that supports Chimera's block-based matching APIs
+#[derive(Clone, Debug)]
+pub struct ChimeraScanner<'db> {
+    scratch: ChimeraScratch,
+    db: &'db ChimeraDatabase,
+}
+
+impl<'db> ChimeraScanner<'db> {
+    /// Create a new scanner with the given database
+    pub fn new(db: &'db ChimeraDatabase) -> Result<Self, Error> {
+        Ok(Self {
+            db,
+            scratch: ChimeraScratch::new(db)?,
+        })
+    }
+
+    /// Create a copy of this scanner, with its own scratch space
+    ///
+    /// Unlike `clone`, this returns an error instead of panicking if the scratch space cannot be
+    /// copied.
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        Ok(Self {
+            db: self.db,
+            scratch: self.scratch.try_clone()?,
+        })
+    }
+
+    /// Scan the input using the given callback functions
+    ///
+    /// The callback functions return a [`ChimeraCallbackResult`] value, such as `ChimeraScan`,
+    /// `Scan`, `Result<Scan, E>`, or `ControlFlow<B>`, which determines what this method returns.
+    /// The match callback function takes 5 arguments:
+    ///
+    /// - id: u32               The ID of the expression that matched
+    /// - from: u64             The offset of the start byte of the match
+    /// - to: u64               The offset of the byte after the end byte of the match
+    /// - flags: u32            Unused; "provided for future use"
+    /// - captures: &[Capture]  The capture groups of the match, when compiled with `ChimeraMode::GROUPS`
+    ///
+    /// The error callback function is invoked when PCRE exceeds one of its limits while matching
+    /// an expression. It takes the error event and the ID of the expression.
+    ///
+    /// Inputs larger than `u32::MAX` bytes cannot be scanned; for such inputs,
+    /// `Error::InputTooLarge` is returned.
+    ///
+    /// For more detail, see the Chimera documentation:
+    ///
+    /// - [`ch_scan`](https://intel.github.io/hyperscan/dev-reference/chimera.html#c.ch_scan)
+    pub fn scan<F, E, R>(&mut self, data: &[u8], on_match: F, on_error: E) -> R::Output
+    where
+        F: FnMut(u32, u64, u64, u32, &[Capture]) -> R,
+        E: FnMut(ChimeraErrorEvent, u32) -> R,
+        R: ChimeraCallbackResult,
+    {
+        let mut context = ChimeraContext {
+            on_match,
+            on_error,
+            stopped: None,
+            panic: None,
+        };
+
+        let Ok(length) = u32::try_from(data.len()) else {
+            return R::into_output(None, Err(Error::InputTooLarge(data.len())));
+        };
+
+        let res = unsafe {
+            hs::ch_scan(
+                self.db.inner.as_ptr(),
+                data.as_ptr() as *const _,
+                length,
+                0,
+                self.scratch.as_ptr(),
+                Some(on_match_trampoline::<F, E, R>),
+                Some(on_error_trampoline::<F, E, R>),
+                &mut context as *mut _ as *mut c_void,
+            )
+            .ok()
+        };
+
+        if let Some(payload) = context.panic {
+            panic::resume_unwind(payload);
+        }
+
+        let res = match res {
+            Ok(_) => Ok(Scan::Continue),
+            Err(err) => match err {
+                Error::Hyperscan(HyperscanErrorCode::ScanTerminated, _) => Ok(Scan::Terminate),
+                err => Err(err),
+            },
+        };
+        R::into_output(context.stopped, res)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// User Context
+// -------------------------------------------------------------------------------------------------
+
+/// Bundles together Rust state to be passed to the Chimera C FFI matching API
+struct ChimeraContext<F, E, R>
+where
+    F: FnMut(u32, u64, u64, u32, &[Capture]) -> R,
+    E: FnMut(ChimeraErrorEvent, u32) -> R,
+    R: ChimeraCallbackResult,
+{
+    on_match: F,
+    on_error: E,
+
+    /// The value returned by one of the callbacks that stopped the scan, if any
+    stopped: Option<R>,
+
+    /// The payload of a panic raised by one of the callbacks, if any, to be resumed after the scan
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+impl<F, E, R> ChimeraContext<F, E, R>
+where
+    F: FnMut(u32, u64, u64, u32, &[Capture]) -> R,
+    E: FnMut(ChimeraErrorEvent, u32) -> R,
+    R: ChimeraCallbackResult,
+{
+    /// Interpret the outcome of a callback, recording the value that stops the scan, if any
+    fn handle(&mut self, outcome: std::thread::Result<R>) -> hs::ch_callback_t {
+        match outcome {
+            Ok(res) => {
+                let action = res.action();
+                if action == ChimeraScan::Terminate {
+                    self.stopped = Some(res);
+                }
+                action.as_callback()
+            }
+            Err(payload) => {
+                self.panic = Some(payload);
+                ChimeraScan::Terminate.as_callback()
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn on_match_trampoline<F, E, R>(
+    id: c_uint,
+    from: c_ulonglong,
+    to: c_ulonglong,
+    flags: c_uint,
+    size: c_uint,
+    captured: *const hs::ch_capture_t,
+    ctx: *mut c_void,
+) -> hs::ch_callback_t
+where
+    F: FnMut(u32, u64, u64, u32, &[Capture]) -> R,
+    E: FnMut(ChimeraErrorEvent, u32) -> R,
+    R: ChimeraCallbackResult,
+{
+    let context = (ctx as *mut ChimeraContext<F, E, R>)
+        .as_mut()
+        .expect("context object should be set");
+    let captures: &[Capture] = if captured.is_null() || size == 0 {
+        &[]
+    } else {
+        // N.B. `Capture` is a `repr(transparent)` wrapper around `ch_capture_t`
+        std::slice::from_raw_parts(captured as *const Capture, size as usize)
+    };
+    let on_match = &mut context.on_match;
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| on_match(id, from, to, flags, captures)));
+    context.handle(outcome)
+}
+
+unsafe extern "C" fn on_error_trampoline<F, E, R>(
+    error_type: hs::ch_error_event_t,
+    id: c_uint,
+    _info: *mut c_void,
+    ctx: *mut c_void,
+) -> c_int
+where
+    F: FnMut(u32, u64, u64, u32, &[Capture]) -> R,
+    E: FnMut(ChimeraErrorEvent, u32) -> R,
+    R: ChimeraCallbackResult,
+{
+    let context = (ctx as *mut ChimeraContext<F, E, R>)
+        .as_mut()
+        .expect("context object should be set");
+    let on_error = &mut context.on_error;
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| on_error(error_type.into(), id)));
+    context.handle(outcome)
+}
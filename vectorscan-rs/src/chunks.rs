@@ -0,0 +1,132 @@
+use std::collections::VecDeque;
+
+// -------------------------------------------------------------------------------------------------
+// Chunk Map
+// -------------------------------------------------------------------------------------------------
+
+/// The chunk of a stream that an offset falls in, as resolved by a [`ChunkMap`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkPosition {
+    /// The 0-based index of the chunk, counting every call that scanned the stream
+    pub index: u64,
+
+    /// The tag given when the chunk was scanned, or its index if it was scanned without a tag
+    pub tag: u64,
+
+    /// The offset relative to the start of the chunk
+    pub offset: u64,
+}
+
+/// A chunk recorded by a `ChunkMap`
+#[derive(Clone, Copy, Debug)]
+struct Chunk {
+    index: u64,
+    tag: u64,
+    start: u64,
+    len: u64,
+}
+
+/// A record of the boundaries of the most recent chunks scanned through a
+/// [`StreamScanner`](crate::StreamScanner), for resolving the absolute offsets reported by a scan
+/// to offsets within the chunks
+///
+/// Up to the number of chunks set by
+/// [`StreamScanner::with_chunk_map`](crate::StreamScanner::with_chunk_map) are kept, which is
+/// only the most recent chunk by default. Chunks scanned without a tag are only recorded once
+/// `with_chunk_map` has been called, so that scanning is not slowed down otherwise; they are
+/// still counted in the chunk indices. The end offset of every match falls within the chunk
+/// whose scan reported it; start offsets of `SOM_LEFTMOST` matches may fall in earlier chunks.
+#[derive(Clone, Debug)]
+pub struct ChunkMap {
+    /// The most recent non-empty chunks, oldest first
+    chunks: VecDeque<Chunk>,
+    max_chunks: usize,
+    /// Whether chunks scanned without a tag are recorded
+    record_untagged: bool,
+    /// The index of the next chunk
+    next_index: u64,
+    /// The total length of the chunks
+    len: u64,
+}
+
+impl Default for ChunkMap {
+    fn default() -> Self {
+        Self {
+            chunks: VecDeque::new(),
+            max_chunks: 1,
+            record_untagged: false,
+            next_index: 0,
+            len: 0,
+        }
+    }
+}
+
+impl ChunkMap {
+    /// Change the number of chunks to keep, which is at least 1, and start recording chunks
+    /// scanned without a tag
+    pub(crate) fn set_max_chunks(&mut self, max_chunks: usize) {
+        self.max_chunks = max_chunks.max(1);
+        self.record_untagged = true;
+        self.trim();
+    }
+
+    /// Record the next chunk, with the given tag or else its index
+    ///
+    /// Chunks without a tag only advance the index and length unless `set_max_chunks` has been
+    /// called.
+    pub(crate) fn push(&mut self, tag: Option<u64>, data: &[u8]) {
+        let index = self.next_index;
+        self.next_index += 1;
+        if data.is_empty() {
+            return;
+        }
+        if tag.is_none() && !self.record_untagged {
+            self.len += data.len() as u64;
+            return;
+        }
+        self.chunks.push_back(Chunk {
+            index,
+            tag: tag.unwrap_or(index),
+            start: self.len,
+            len: data.len() as u64,
+        });
+        self.len += data.len() as u64;
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.chunks.len() > self.max_chunks {
+            self.chunks.pop_front();
+        }
+    }
+
+    /// Resolve the offset of a byte to the chunk containing it
+    ///
+    /// Returns `None` if the byte is not in any of the chunks kept.
+    pub fn resolve(&self, offset: u64) -> Option<ChunkPosition> {
+        let i = self
+            .chunks
+            .partition_point(|chunk| chunk.start + chunk.len <= offset);
+        let chunk = self.chunks.get(i).filter(|chunk| chunk.start <= offset)?;
+        Some(ChunkPosition {
+            index: chunk.index,
+            tag: chunk.tag,
+            offset: offset - chunk.start,
+        })
+    }
+
+    /// Resolve the end offset of a match, the offset of the byte after its last byte, to the
+    /// chunk containing its last byte
+    ///
+    /// The resolved offset can equal the length of the chunk. Empty matches at offset 0 resolve
+    /// to the first chunk. Returns `None` if the last byte is not in any of the chunks kept.
+    pub fn resolve_end(&self, end: u64) -> Option<ChunkPosition> {
+        match end.checked_sub(1) {
+            Some(last) => self.resolve(last).map(|pos| ChunkPosition {
+                offset: pos.offset + 1,
+                ..pos
+            }),
+            None => self.resolve(0),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::io::{self, Read};
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use xz2::read::XzDecoder;
+
+use super::{CallbackResult, Error, ReaderScan, Scan, StreamingScanner};
+
+// -------------------------------------------------------------------------------------------------
+// Compression Formats
+// -------------------------------------------------------------------------------------------------
+
+/// A compression format, as detected from the magic bytes at the start of an input
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Compression {
+    /// The input is not compressed in any of the supported formats
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+    Bzip2,
+}
+
+impl Compression {
+    /// The longest magic byte sequence of the supported formats
+    const MAX_MAGIC_LEN: usize = 10;
+
+    /// Detect the compression format of an input from its first bytes
+    ///
+    /// At least 10 bytes are needed to detect every format, unless the input is shorter.
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Self::Gzip
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Self::Zstd
+        } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Self::Xz
+        } else if is_bzip2(header) {
+            Self::Bzip2
+        } else {
+            Self::None
+        }
+    }
+}
+
+/// Check for the bzip2 stream header: "BZh", a block size from 1 to 9, and the magic bytes of
+/// either the first block or the end of an empty stream
+///
+/// Checking more than "BZh" avoids treating plain text that starts with it as compressed.
+fn is_bzip2(header: &[u8]) -> bool {
+    const BLOCK_MAGIC: [u8; 6] = [0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+    const END_MAGIC: [u8; 6] = [0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+
+    match header {
+        [b'B', b'Z', b'h', b'1'..=b'9', magic @ ..] => {
+            magic.starts_with(&BLOCK_MAGIC) || magic.starts_with(&END_MAGIC)
+        }
+        _ => false,
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// Decompressing Scans
+// -------------------------------------------------------------------------------------------------
+
+impl<'db> StreamingScanner<'db> {
+    /// Scan everything read from the given reader as a single stream, decompressing it first if
+    /// it is compressed with gzip, zstd, xz, or bzip2
+    ///
+    /// The compression format is detected from the magic bytes at the start of the input, and
+    /// input in any other format is scanned as is. Concatenated compressed members, as produced by
+    /// `cat a.gz b.gz`, are decompressed as one input. Match offsets are relative to the start of
+    /// the decompressed data.
+    ///
+    /// To defend against decompression bombs, at most `max_size` bytes of decompressed data are
+    /// scanned; if the input decompresses to more than that, the stream is discarded and
+    /// `Error::DecompressionLimit` is returned. Errors from the reader or from decompression are
+    /// returned as `Error::Io`.
+    ///
+    /// Returns the outcome of the scan along with the number of decompressed bytes scanned. See
+    /// [`StreamingScanner::scan_buf_reader`] for details of the callback function.
+    pub fn scan_compressed_reader<T, F, R>(
+        &mut self,
+        mut reader: T,
+        max_size: u64,
+        mut on_match: F,
+    ) -> ReaderScan<R::Output>
+    where
+        T: Read,
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        let mut header = Vec::with_capacity(Compression::MAX_MAGIC_LEN);
+        if let Err(e) = (&mut reader)
+            .take(Compression::MAX_MAGIC_LEN as u64)
+            .read_to_end(&mut header)
+        {
+            return ReaderScan {
+                bytes: 0,
+                outcome: R::into_output(None, Err(e.into())),
+            };
+        }
+        let compression = Compression::detect(&header);
+        let reader = io::Cursor::new(header).chain(reader);
+
+        let reader: Box<dyn Read + '_> = match compression {
+            Compression::None => Box::new(reader),
+            Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+            Compression::Zstd => match zstd::stream::read::Decoder::new(reader) {
+                Ok(decoder) => Box::new(decoder),
+                Err(e) => {
+                    return ReaderScan {
+                        bytes: 0,
+                        outcome: R::into_output(None, Err(e.into())),
+                    }
+                }
+            },
+            Compression::Xz => Box::new(XzDecoder::new_multi_decoder(reader)),
+            Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+        };
+        let mut limited = LimitedReader {
+            inner: reader,
+            remaining: max_size,
+            exceeded: false,
+        };
+        let mut stopped = None;
+        let scan = self.scan_reader(&mut limited, |id, from, to, flags| {
+            let res = on_match(id, from, to, flags);
+            if res.should_continue() {
+                Scan::Continue
+            } else {
+                stopped = Some(res);
+                Scan::Terminate
+            }
+        });
+        let res = match scan.outcome {
+            Err(_) if limited.exceeded => Err(Error::DecompressionLimit(max_size)),
+            res => res,
+        };
+        ReaderScan {
+            bytes: scan.bytes,
+            outcome: R::into_output(stopped, res),
+        }
+    }
+}
+
+/// A reader that fails once more than a given number of bytes would be read
+struct LimitedReader<R> {
+    inner: R,
+    remaining: u64,
+    /// Whether reading failed because the limit was exceeded
+    exceeded: bool,
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            // Read one more byte to tell the end of the input from an input that is too large
+            if self.inner.read(&mut [0])? == 0 {
+                return Ok(0);
+            }
+            self.exceeded = true;
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "decompressed input exceeds the size limit",
+            ));
+        }
+        let max = usize::try_from(self.remaining).map_or(buf.len(), |r| r.min(buf.len()));
+        let n = self.inner.read(&mut buf[..max])?;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
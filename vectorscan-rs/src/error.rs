@@ -1,3 +1,7 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
 use thiserror::Error;
 use vectorscan_rs_sys as ffi;
 
@@ -12,6 +16,104 @@ pub enum Error {
 
     #[error("Pattern compilation failed, {0} at {1}")]
     HyperscanCompile(String, i32),
+
+    #[error("Input of {0} bytes is too large to scan in block mode")]
+    InputTooLarge(usize),
+
+    #[error("Scratch space was not allocated for this database")]
+    ScratchMismatch,
+
+    #[error("Decompressed input exceeds the limit of {0} bytes")]
+    DecompressionLimit(u64),
+
+    #[error(
+        "Archive {kind} limit of {limit} bytes exceeded at {}",
+        display_entry_path(.path)
+    )]
+    ArchiveLimit {
+        /// The limit that was exceeded
+        kind: ArchiveLimitKind,
+        /// The value of the limit, in bytes
+        limit: u64,
+        /// The chain of entry paths leading to the entry where the limit was exceeded, starting
+        /// with the path within the outermost archive; empty if it was exceeded by the input itself
+        path: Vec<PathBuf>,
+    },
+
+    #[error("I/O error while reading input: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A size limit on archives that was exceeded, as reported by `Error::ArchiveLimit`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveLimitKind {
+    /// The maximum size of a single entry, `ArchiveLimits::max_entry_size`
+    EntrySize,
+
+    /// The maximum total size of all the entries scanned, `ArchiveLimits::max_total_size`
+    TotalSize,
+
+    /// The maximum total size of nested zip archives held in memory at once,
+    /// `ArchiveLimits::max_buffered_size`
+    BufferedSize,
+}
+
+impl fmt::Display for ArchiveLimitKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::EntrySize => "entry size",
+            Self::TotalSize => "total size",
+            Self::BufferedSize => "buffered size",
+        })
+    }
+}
+
+/// Display a chain of archive entry paths
+fn display_entry_path(path: &[PathBuf]) -> String {
+    if path.is_empty() {
+        return "<input>".to_owned();
+    }
+    path.iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(" > ")
+}
+
+/// A failure to free a Vectorscan or Chimera object when it was dropped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FreeFailure {
+    /// The name of the C function that failed, such as `hs_free_database`
+    pub function: &'static str,
+
+    /// The error code it returned
+    pub code: i32,
+}
+
+/// The number of failures to free objects on drop
+static FREE_FAILURE_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// The most recent failure to free an object on drop
+static LAST_FREE_FAILURE: Mutex<Option<FreeFailure>> = Mutex::new(None);
+
+/// Get the number of times freeing a database, scratch space, compile error, or stream failed
+/// when it was dropped, with the most recent failure
+///
+/// Errors cannot be returned from `drop`, and panicking there aborts the process if it is already
+/// unwinding, so these failures are recorded instead. They indicate a bug, such as memory
+/// corruption.
+pub fn free_failures() -> (u64, Option<FreeFailure>) {
+    let last = *LAST_FREE_FAILURE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    (FREE_FAILURE_COUNT.load(Ordering::Relaxed), last)
+}
+
+/// Record a failure to free an object on drop, for `free_failures`
+pub(crate) fn report_free_failure(function: &'static str, code: i32) {
+    FREE_FAILURE_COUNT.fetch_add(1, Ordering::Relaxed);
+    *LAST_FREE_FAILURE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = Some(FreeFailure { function, code });
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -134,6 +236,16 @@ impl From<ffi::hs_error_t> for Error {
         Error::Hyperscan(err.into(), err)
     }
 }
+
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            err => std::io::Error::new(std::io::ErrorKind::Other, err),
+        }
+    }
+}
+
 pub trait AsResult: Sized {
     fn ok(self) -> Result<(), Error>;
 }
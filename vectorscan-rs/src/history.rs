@@ -0,0 +1,139 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use super::Match;
+
+// -------------------------------------------------------------------------------------------------
+// Stream History
+// -------------------------------------------------------------------------------------------------
+
+/// The most recent bytes of a stream, kept so that match callbacks can retrieve matched text
+/// that began in earlier chunks
+#[derive(Debug, Default)]
+pub(crate) struct HistoryBuffer {
+    /// The number of bytes of earlier chunks to keep
+    window: usize,
+    /// The last bytes of the chunks scanned so far, up to `window` bytes
+    buffer: Vec<u8>,
+    /// The stream offset of the first byte of `buffer`
+    start: u64,
+}
+
+impl HistoryBuffer {
+    /// Change the number of bytes of earlier chunks to keep
+    pub(crate) fn set_window(&mut self, window: usize) {
+        self.window = window;
+        self.trim();
+    }
+
+    /// Record a chunk of the stream once it has been scanned, keeping only its last `window`
+    /// bytes
+    pub(crate) fn push(&mut self, chunk: &[u8]) {
+        let kept = &chunk[chunk.len().saturating_sub(self.window)..];
+        if kept.len() < chunk.len() {
+            // None of the bytes kept so far are in the window anymore
+            self.start += (self.buffer.len() + chunk.len() - kept.len()) as u64;
+            self.buffer.clear();
+        }
+        self.buffer.extend_from_slice(kept);
+        self.trim();
+    }
+
+    /// Discard all but the last `window` bytes
+    fn trim(&mut self) {
+        if let Some(excess) = self.buffer.len().checked_sub(self.window) {
+            self.buffer.drain(..excess);
+            self.start += excess as u64;
+        }
+    }
+
+    /// Get a view of the history, where `chunk` is the chunk being scanned, which has not been
+    /// pushed yet
+    pub(crate) fn view<'a>(&'a self, chunk: &'a [u8]) -> History<'a> {
+        History {
+            earlier: &self.buffer,
+            chunk,
+            start: self.start,
+        }
+    }
+}
+
+/// A view of the most recent bytes of a stream, given to match callbacks by
+/// [`StreamScanner::scan_with_history`](crate::StreamScanner::scan_with_history)
+///
+/// The view covers the chunk being scanned, preceded by up to the configured window of bytes
+/// from earlier chunks. The chunk is borrowed rather than copied, so bytes that span the chunk
+/// and earlier chunks are copied when they are retrieved.
+#[derive(Clone, Copy, Debug)]
+pub struct History<'a> {
+    /// The bytes kept from earlier chunks
+    earlier: &'a [u8],
+    /// The chunk being scanned, which follows `earlier`
+    chunk: &'a [u8],
+    /// The stream offset of the first byte of `earlier`
+    start: u64,
+}
+
+/// The bytes of a match together with the bytes around it, from a [`History`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MatchContext<'a> {
+    /// The bytes before the match; fewer than requested if they are unavailable
+    pub before: Cow<'a, [u8]>,
+
+    /// The bytes of the match
+    pub matched: Cow<'a, [u8]>,
+
+    /// The bytes after the match; fewer than requested if they have not been scanned yet
+    pub after: Cow<'a, [u8]>,
+}
+
+impl<'a> History<'a> {
+    /// Get the stream offsets of the bytes available
+    pub fn range(&self) -> Range<u64> {
+        self.start..self.start + (self.earlier.len() + self.chunk.len()) as u64
+    }
+
+    /// Get the bytes at the given stream offsets, or `None` if any of them are unavailable
+    ///
+    /// The bytes are borrowed if they lie within the chunk being scanned or within the bytes of
+    /// earlier chunks, and are copied if they span both.
+    pub fn get(&self, range: Range<u64>) -> Option<Cow<'a, [u8]>> {
+        let start = usize::try_from(range.start.checked_sub(self.start)?).ok()?;
+        let end = usize::try_from(range.end.checked_sub(self.start)?).ok()?;
+        let split = self.earlier.len();
+        if end <= split {
+            self.earlier.get(start..end).map(Cow::Borrowed)
+        } else if start >= split {
+            self.chunk
+                .get(start - split..end - split)
+                .map(Cow::Borrowed)
+        } else {
+            let head = self.earlier.get(start..)?;
+            let tail = self.chunk.get(..end - split)?;
+            Some(Cow::Owned([head, tail].concat()))
+        }
+    }
+
+    /// Get the bytes of the given match, or `None` if they are unavailable because the match
+    /// began before the window
+    ///
+    /// Without `Flag::SOM_LEFTMOST`, the start of every match is reported as 0, so the bytes of
+    /// a match are only available while the window covers the whole stream.
+    pub fn matched(&self, m: &Match) -> Option<Cow<'a, [u8]>> {
+        self.get(m.range())
+    }
+
+    /// Get the bytes of the given match with up to `before` bytes before it and up to `after`
+    /// bytes after it, or `None` if the bytes of the match are unavailable
+    pub fn context(&self, m: &Match, before: usize, after: usize) -> Option<MatchContext<'a>> {
+        let matched = self.matched(m)?;
+        let range = self.range();
+        let before_start = m.start.saturating_sub(before as u64).max(range.start);
+        let after_end = m.end.saturating_add(after as u64).min(range.end);
+        Some(MatchContext {
+            before: self.get(before_start..m.start)?,
+            matched,
+            after: self.get(m.end..after_end)?,
+        })
+    }
+}
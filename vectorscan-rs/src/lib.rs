@@ -1,17 +1,51 @@
 //! Vectorscan is a fork of [Hyperscan](https://github.com/Intel/hyperscan) that supports additional platforms.
 //! To understand how to use this library, it may be helpful to look at the [documentation for the Hyperscan C bindings](https://intel.github.io/hyperscan/dev-reference/).
 
+#[cfg(feature = "archive")]
+mod archive;
+#[cfg(feature = "tokio")]
+mod async_scan;
+#[cfg(feature = "rayon")]
+mod batch;
+#[cfg(feature = "chimera")]
+mod chimera;
+mod chunks;
+#[cfg(feature = "compression")]
+mod decompress;
 mod error;
+mod history;
+mod lines;
+#[cfg(feature = "mmap")]
+mod mmap;
 mod native;
+mod pool;
 mod wrapper;
+mod writer;
 
-pub use error::{AsResult, Error, HyperscanErrorCode};
+#[cfg(feature = "archive")]
+pub use archive::{ArchiveLimits, ArchiveScan};
+#[cfg(feature = "tokio")]
+pub use async_scan::{ChunkMatches, ReaderMatches};
+#[cfg(feature = "chimera")]
+pub use chimera::*;
+pub use chunks::{ChunkMap, ChunkPosition};
+#[cfg(feature = "compression")]
+pub use decompress::Compression;
+pub use error::{
+    free_failures, ArchiveLimitKind, AsResult, Error, FreeFailure, HyperscanErrorCode,
+};
+pub use history::{History, MatchContext};
+pub use lines::{LineIndex, LinePosition};
 pub use native::*;
-pub use wrapper::{Flag, Pattern, ScanMode, Scratch};
+pub use pool::{OwnedScratchGuard, ScratchGuard, ScratchPool};
+pub use wrapper::{ExpressionInfo, Flag, Pattern, ScanMode};
+pub use writer::ScanWriter;
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::ops::{ControlFlow, Range};
+    use std::sync::Arc;
 
     #[test]
     fn block_scanning_basic() -> Result<(), Error> {
@@ -66,6 +100,7 @@ mod tests {
             let expected = &[(0, 0, 5, 0), (0, 0, 11, 0)];
             assert_eq!(matches.as_slice(), expected);
 
+            #[allow(deprecated)]
             scanner.close(|id, from, to, flags| {
                 matches.push((id, from, to, flags));
                 Scan::Continue
@@ -94,13 +129,1214 @@ mod tests {
         let _db = wrapper::Database::new(vec![], ScanMode::BLOCK).unwrap();
     }
 
+    #[test]
+    fn scanner_try_clone() -> Result<(), Error> {
+        let patterns = vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)];
+        let db = BlockDatabase::new(patterns)?;
+        let scanner = db.create_scanner()?;
+        let mut scanner2 = scanner.try_clone()?;
+        drop(scanner);
+        assert_eq!(scanner2.find_all(b"hello")?, vec![Match::new(0, 0, 5)]);
+
+        let db =
+            StreamingDatabase::new(vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)])?;
+        let scanner = OwnedStreamingScanner::new(db)?;
+        let scanner2 = scanner.try_clone()?;
+        drop(scanner);
+        let mut stream = scanner2.into_stream()?;
+        stream.scan(b"hel", |_, _, _, _| Scan::Continue)?;
+        assert_eq!(stream.find_all(b"lo")?, vec![Match::new(0, 0, 5)]);
+        stream.discard()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn scratch_database_pairing() -> Result<(), Error> {
+        let db1 = BlockDatabase::new(vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)])?;
+        let db2 = BlockDatabase::new(vec![Pattern::new(b"world".to_vec(), Flag::default(), None)])?;
+
+        let scratch = Scratch::new(&db1)?;
+        assert!(scratch.is_valid_for(&db1));
+        assert!(scratch.is_valid_for(&db1.clone()));
+        assert!(!scratch.is_valid_for(&db2));
+        assert!(!scratch.is_valid_for(&db1.deep_copy()?));
+
+        let err = BlockScanner::with_scratch(&db2, scratch.clone()).unwrap_err();
+        assert!(matches!(err, Error::ScratchMismatch));
+
+        let mut scanner = BlockScanner::with_scratch(&db1, scratch)?;
+        assert_eq!(scanner.find_all(b"hello world")?, vec![Match::new(0, 0, 5)]);
+
+        let sdb =
+            StreamingDatabase::new(vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)])?;
+        let scratch = scanner.into_scratch();
+        assert!(StreamingScanner::with_scratch(&sdb, scratch).is_err());
+
+        let mut scanner = StreamingScanner::with_scratch(&sdb, Scratch::new(&sdb)?)?;
+        let mut stream = scanner.open_stream()?;
+        stream.scan(b"hel", |_, _, _, _| Scan::Continue)?;
+        assert_eq!(stream.find_all(b"lo")?, vec![Match::new(0, 0, 5)]);
+        stream.discard()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn standalone_stream_across_threads() -> Result<(), Error> {
+        let db =
+            StreamingDatabase::new(vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)])?;
+        let mut scratch = Scratch::new(&db)?;
+        let mut stream = assert_send_static(Stream::new(&db)?);
+        assert_eq!(stream.find_all(&mut scratch, b"hel")?, vec![]);
+
+        // Continue the stream on another thread, using that thread's own scratch space
+        let thread_scratch = scratch.clone();
+        let mut stream = std::thread::spawn(move || -> Result<Stream, Error> {
+            let mut scratch = thread_scratch;
+            assert_eq!(
+                stream.find_all(&mut scratch, b"lo")?,
+                vec![Match::new(0, 0, 5)]
+            );
+            Ok(stream)
+        })
+        .join()
+        .unwrap()?;
+
+        // Scratch space for another database is rejected
+        let other =
+            StreamingDatabase::new(vec![Pattern::new(b"world".to_vec(), Flag::default(), None)])?;
+        let mut other_scratch = Scratch::new(&other)?;
+        let err = stream.scan(&mut other_scratch, b"hello", |_, _, _, _| Scan::Continue);
+        assert!(matches!(err, Err(Error::ScratchMismatch)));
+
+        assert_eq!(
+            stream.find_all(&mut scratch, b" hello")?,
+            vec![Match::new(0, 0, 11)]
+        );
+        assert_eq!(stream.close_find_all(&mut scratch)?, vec![]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn scratch_pool() -> Result<(), Error> {
+        let db = BlockDatabase::new(vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)])?;
+        assert!(matches!(
+            ScratchPool::with_max_size(&db, 0),
+            Err(Error::Hyperscan(HyperscanErrorCode::Invalid, _))
+        ));
+        let pool = ScratchPool::with_max_size(&db, 2)?;
+        assert!(pool.is_valid_for(&db));
+        assert_eq!(pool.size(), 1);
+
+        std::thread::scope(|s| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    s.spawn(|| -> Result<(), Error> {
+                        for _ in 0..100 {
+                            let mut scratch = pool.get()?;
+                            let mut matches = Vec::new();
+                            db.scan_with(&mut scratch, b"hello hello", |id, from, to, _flags| {
+                                matches.push(Match::new(id, from, to));
+                                Scan::Continue
+                            })?;
+                            assert_eq!(matches, vec![Match::new(0, 0, 5), Match::new(0, 0, 11)]);
+                        }
+                        Ok(())
+                    })
+                })
+                .collect();
+            handles.into_iter().try_for_each(|h| h.join().unwrap())
+        })?;
+        assert!(pool.size() <= 2);
+
+        let a = pool.get()?;
+        let b = pool.try_get()?.expect("pool should have room");
+        assert!(pool.try_get()?.is_none());
+        drop(a);
+        assert!(pool.try_get()?.is_some());
+        drop(b);
+
+        let other =
+            BlockDatabase::new(vec![Pattern::new(b"world".to_vec(), Flag::default(), None)])?;
+        let mut scratch = pool.get()?;
+        let res = other.scan_with(&mut scratch, b"world", |_, _, _, _| Scan::Continue);
+        assert!(matches!(res, Err(Error::ScratchMismatch)));
+        drop(scratch);
+
+        let pool = Arc::new(pool);
+        let db = Arc::new(db);
+        let a = pool.get_owned()?;
+        let b = pool.try_get_owned()?.expect("pool should have room");
+        assert!(pool.try_get_owned()?.is_none());
+        let handle = std::thread::spawn({
+            let db = db.clone();
+            move || -> Result<u64, Error> {
+                let mut scratch = a;
+                let mut count = 0;
+                db.scan_with(&mut scratch, b"hello", |_, _, _, _| {
+                    count += 1;
+                    Scan::Continue
+                })?;
+                Ok(count)
+            }
+        });
+        assert_eq!(handle.join().unwrap()?, 1);
+        assert!(Arc::ptr_eq(b.pool(), &pool));
+        assert!(pool.try_get_owned()?.is_some());
+        drop(b);
+        assert_eq!(pool.size(), 2);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn scratch_pool_acquire() -> Result<(), Error> {
+        let db = Arc::new(BlockDatabase::new(vec![Pattern::new(
+            b"hello".to_vec(),
+            Flag::default(),
+            None,
+        )])?);
+        let pool = Arc::new(ScratchPool::with_max_size(db.as_ref(), 1)?);
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+        runtime.block_on(async {
+            let held = pool.acquire().await?;
+            let task = tokio::spawn({
+                let db = db.clone();
+                let pool = pool.clone();
+                async move {
+                    // Waits until `held` is dropped, without blocking the runtime's only thread
+                    let mut scratch = pool.acquire_owned().await?;
+                    let mut count = 0;
+                    db.scan_with(&mut scratch, b"hello hello", |_, _, _, _| {
+                        count += 1;
+                        Scan::Continue
+                    })?;
+                    Ok::<_, Error>(count)
+                }
+            });
+            tokio::task::yield_now().await;
+            assert!(!task.is_finished());
+            drop(held);
+            assert_eq!(task.await.unwrap()?, 2);
+            assert_eq!(pool.size(), 1);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn block_database_thread_local_scan() -> Result<(), Error> {
+        let db1 = Arc::new(BlockDatabase::new(vec![Pattern::new(
+            b"hello".to_vec(),
+            Flag::default(),
+            None,
+        )])?);
+        let db2 = BlockDatabase::new(vec![Pattern::new(b"world".to_vec(), Flag::default(), None)])?;
+
+        // Alternate between databases on the same thread
+        for _ in 0..10 {
+            assert_eq!(db1.find_all(b"hello world")?, vec![Match::new(0, 0, 5)]);
+            assert_eq!(db2.find_all(b"hello world")?, vec![Match::new(0, 0, 11)]);
+        }
+
+        // Scanning with more databases than the cached scratch space is grown for replaces it
+        for i in 0..10 {
+            let db = BlockDatabase::new(vec![Pattern::new(
+                format!("hello{i}").into_bytes(),
+                Flag::default(),
+                None,
+            )])?;
+            assert_eq!(
+                db.find_all(b"hello7")?,
+                if i == 7 {
+                    vec![Match::new(0, 0, 6)]
+                } else {
+                    vec![]
+                }
+            );
+            assert_eq!(db1.find_all(b"hello world")?, vec![Match::new(0, 0, 5)]);
+        }
+
+        // Scanning again from within a match callback uses a temporary scratch space
+        let mut inner_matches = Vec::new();
+        db1.scan(b"hello", |_id, _from, _to, _flags| -> Result<Scan, Error> {
+            inner_matches.extend(db2.find_all(b"world")?);
+            Ok(Scan::Continue)
+        })?;
+        assert_eq!(inner_matches, vec![Match::new(0, 0, 5)]);
+
+        // Share the database between threads without a scanner per thread
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let db = db1.clone();
+                std::thread::spawn(move || db.find_all(b"hello hello"))
+            })
+            .collect();
+        for h in handles {
+            assert_eq!(
+                h.join().unwrap()?,
+                vec![Match::new(0, 0, 5), Match::new(0, 0, 11)]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn scratch_for_several_databases() -> Result<(), Error> {
+        let db1 = BlockDatabase::new(vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)])?;
+        let db2 = BlockDatabase::new(vec![Pattern::new(
+            b"w.r.d".to_vec(),
+            Flag::default(),
+            Some(7),
+        )])?;
+        let sdb =
+            StreamingDatabase::new(vec![Pattern::new(b"lo wo".to_vec(), Flag::default(), None)])?;
+        let other = BlockDatabase::new(vec![Pattern::new(b"x".to_vec(), Flag::default(), None)])?;
+
+        assert!(Scratch::for_databases(&[]).is_err());
+        let mut scratch = Scratch::for_databases(&[&db1, &db2, &sdb])?;
+        assert!(scratch.is_valid_for(&db1));
+        assert!(scratch.is_valid_for(&db2));
+        assert!(scratch.is_valid_for(&sdb));
+        assert!(!scratch.is_valid_for(&other));
+
+        let data = b"hello world";
+        let mut matches = Vec::new();
+        for db in [&db1, &db2] {
+            db.scan_with(&mut scratch, data, |id, from, to, _flags| {
+                matches.push(Match::new(id, from, to));
+                Scan::Continue
+            })?;
+        }
+        assert_eq!(matches, vec![Match::new(0, 0, 5), Match::new(7, 0, 11)]);
+
+        let mut stream = Stream::new(&sdb)?;
+        assert_eq!(
+            stream.find_all(&mut scratch, data)?,
+            vec![Match::new(0, 0, 8)]
+        );
+        stream.discard()?;
+
+        let mut scanner = BlockScanner::with_scratch(&db2, scratch)?;
+        assert_eq!(scanner.find_all(data)?, vec![Match::new(7, 0, 11)]);
+        let scratch = scanner.into_scratch().add_database(&other)?;
+        assert!(scratch.is_valid_for(&other) && scratch.is_valid_for(&db1));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn block_scan_batch() -> Result<(), Error> {
+        let db = BlockDatabase::new(vec![
+            Pattern::new(b"hello".to_vec(), Flag::default(), Some(1)),
+            Pattern::new(b"world".to_vec(), Flag::default(), Some(2)),
+        ])?;
+
+        let inputs: Vec<String> = (0..10_000)
+            .map(|i| match i % 3 {
+                0 => format!("{i} hello"),
+                1 => format!("{i} world hello"),
+                _ => format!("{i}"),
+            })
+            .collect();
+
+        let results = db.scan_batch(&inputs)?;
+        assert_eq!(results.len(), inputs.len());
+        for (input, matches) in inputs.iter().zip(&results) {
+            assert_eq!(matches, &db.find_all(input.as_bytes())?);
+        }
+
+        let counts = db.fold_batch(&inputs, || 0, |n, _m| n + 1)?;
+        let expected: Vec<usize> = (0..10_000).map(|i| [1, 2, 0][i % 3]).collect();
+        assert_eq!(counts, expected);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn block_find_all_parallel() -> Result<(), Error> {
+        let db = BlockDatabase::new(vec![
+            Pattern::new(b"hello".to_vec(), Flag::default(), Some(1)),
+            Pattern::new(b"\\bwor[a-z]{1,3}\\b".to_vec(), Flag::SOM_LEFTMOST, Some(2)),
+            Pattern::new(b"^line \\d{1,3}$".to_vec(), Flag::MULTILINE, Some(3)),
+            Pattern::new(b"^hello".to_vec(), Flag::default(), Some(4)),
+            Pattern::new(b"\\d{2}$".to_vec(), Flag::default(), Some(5)),
+        ])?;
+        assert_eq!(db.patterns().len(), 5);
+
+        let data: Vec<u8> = (0..200)
+            .map(|i| format!("hello world line {i}\nline {i}\nworlds words hello{i}\n"))
+            .collect::<String>()
+            .into_bytes();
+
+        let mut expected = db.find_all(&data)?;
+        expected.sort();
+        for chunk_size in [1, 2, 3, 7, 16, 100, 1000, data.len()] {
+            let mut matches = db.find_all_parallel_with_chunk_size(&data, chunk_size)?;
+            matches.sort();
+            assert_eq!(matches, expected, "chunk size {chunk_size}");
+        }
+        let mut matches = db.find_all_parallel(&data)?;
+        matches.sort();
+        assert_eq!(matches, expected);
+
+        // Word boundaries next to multi-byte characters see the whole character
+        let db = BlockDatabase::new(vec![Pattern::new(
+            b"\\bw\\w{2}\\b".to_vec(),
+            Flag::UTF8 | Flag::UCP | Flag::SOM_LEFTMOST,
+            Some(1),
+        )])?;
+        let data = "éwor wor€ wör 𝔸wor wor".repeat(20).into_bytes();
+        let mut expected = db.find_all(&data)?;
+        expected.sort();
+        assert!(!expected.is_empty());
+        for chunk_size in [1, 2, 3, 5, 7, 16, data.len()] {
+            let mut matches = db.find_all_parallel_with_chunk_size(&data, chunk_size)?;
+            matches.sort();
+            assert_eq!(matches, expected, "chunk size {chunk_size}");
+        }
+
+        // Unbounded patterns are scanned without chunking
+        let db = BlockDatabase::new(vec![Pattern::new(b"h.*o".to_vec(), Flag::default(), None)])?;
+        assert_eq!(db.patterns()[0].info()?.max_width, None);
+        assert_eq!(
+            db.find_all_parallel_with_chunk_size(&data, 10)?,
+            db.find_all(&data)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_scan_reader() -> Result<(), Error> {
+        let db = StreamingDatabase::new(vec![
+            Pattern::new(b"hello".to_vec(), Flag::default(), Some(1)),
+            Pattern::new(b"world$".to_vec(), Flag::default(), Some(2)),
+        ])?;
+        let mut scanner = StreamingScanner::new(&db)?;
+        let data = b"hello hello world";
+
+        let expected = vec![
+            Match::new(1, 0, 5),
+            Match::new(1, 0, 11),
+            Match::new(2, 0, 17),
+        ];
+
+        // A buffer size of 0 is treated as 1
+        for buffer_size in [4, 0] {
+            let mut matches = Vec::new();
+            let scan = scanner
+                .scan_reader_with_buffer_size(&data[..], buffer_size, |id, from, to, _flags| {
+                    matches.push(Match::new(id, from, to));
+                    Scan::Continue
+                })
+                .transpose()?;
+            assert_eq!(scan.outcome, Scan::Continue);
+            assert_eq!(scan.bytes, data.len() as u64);
+            assert_eq!(matches, expected);
+        }
+
+        // Terminating stops reading after the buffer containing the match
+        let mut cursor = std::io::Cursor::new(data);
+        let scan = scanner
+            .scan_reader_with_buffer_size(&mut cursor, 4, |_, _, _, _| Scan::Terminate)
+            .transpose()?;
+        assert_eq!(
+            scan,
+            ReaderScan {
+                bytes: 8,
+                outcome: Scan::Terminate
+            }
+        );
+        assert_eq!(cursor.position(), 8);
+
+        let mut matches = Vec::new();
+        let scan = scanner.scan_buf_reader(std::io::Cursor::new(data), |id, from, to, _flags| {
+            matches.push(Match::new(id, from, to));
+            Scan::Continue
+        });
+        assert_eq!(scan.bytes, data.len() as u64);
+        assert_eq!(scan.outcome?, Scan::Continue);
+        assert_eq!(matches, expected);
+
+        // The callback function can return any `CallbackResult`
+        let scan = scanner
+            .scan_reader(&data[..], |id, _from, to, _flags| {
+                if to > 5 {
+                    ControlFlow::Break(id)
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .transpose()?;
+        assert_eq!(scan.outcome, ControlFlow::Break(1));
+        assert_eq!(scan.bytes, data.len() as u64);
+
+        // Errors from the reader are returned, along with the number of bytes scanned before them
+        struct FailingReader(usize);
+        impl std::io::Read for FailingReader {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.0 == 0 {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::BrokenPipe,
+                        "broken",
+                    ));
+                }
+                let n = self.0.min(buf.len());
+                buf[..n].fill(b'a');
+                self.0 -= n;
+                Ok(n)
+            }
+        }
+        let scan =
+            scanner.scan_reader_with_buffer_size(FailingReader(6), 4, |_, _, _, _| Scan::Continue);
+        assert_eq!(scan.bytes, 6);
+        let err = scan.outcome.unwrap_err();
+        assert!(matches!(&err, Error::Io(e) if e.kind() == std::io::ErrorKind::BrokenPipe));
+        assert_eq!(
+            std::io::Error::from(err).kind(),
+            std::io::ErrorKind::BrokenPipe
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_scan_writer() -> Result<(), Error> {
+        use std::io::Write;
+
+        let db = StreamingDatabase::new(vec![
+            Pattern::new(b"hello".to_vec(), Flag::default(), Some(1)),
+            Pattern::new(b"world$".to_vec(), Flag::default(), Some(2)),
+        ])?;
+        let mut scanner = StreamingScanner::new(&db)?;
+        let data = b"hello hello world";
+
+        let mut matches = Vec::new();
+        let mut writer = ScanWriter::with_inner(
+            scanner.open_stream()?,
+            Vec::new(),
+            |id, from, to, _flags| {
+                matches.push(Match::new(id, from, to));
+                Scan::Continue
+            },
+        );
+        std::io::copy(&mut &data[..], &mut writer)?;
+        let (written, res) = writer.finish();
+        assert_eq!(res?, Scan::Continue);
+        assert_eq!(written, data);
+        assert_eq!(
+            matches,
+            vec![
+                Match::new(1, 0, 5),
+                Match::new(1, 0, 11),
+                Match::new(2, 0, 17)
+            ]
+        );
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut writer = ScanWriter::with_sender(scanner.open_stream()?, std::io::sink(), sender);
+        for chunk in data.chunks(3) {
+            writer.write_all(chunk)?;
+        }
+        writer.finish().1?;
+        assert_eq!(
+            receiver.iter().collect::<Vec<_>>(),
+            vec![
+                Match::new(1, 0, 5),
+                Match::new(1, 0, 11),
+                Match::new(2, 0, 17)
+            ]
+        );
+
+        let mut count = 0;
+        let mut writer = ScanWriter::new(scanner.open_stream()?, |_, _, _, _| {
+            count += 1;
+            Scan::Terminate
+        });
+        writer.write_all(data)?;
+        writer.write_all(data)?;
+        assert_eq!(writer.finish().1?, Scan::Terminate);
+        assert_eq!(count, 1);
+
+        // The callback function can return any `CallbackResult`
+        let mut writer = ScanWriter::new(scanner.open_stream()?, |id, _from, to, _flags| {
+            if to > 5 {
+                ControlFlow::Break(id)
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        writer.write_all(data)?;
+        assert_eq!(writer.finish().1?, ControlFlow::Break(1));
+
+        // Errors from scanning don't fail writes whose bytes were forwarded, and are reported by
+        // `finish`
+        let mut writer = ScanWriter::with_inner(
+            scanner.open_stream()?,
+            Vec::new(),
+            |_, _, _, _| -> Result<Scan, Error> {
+                Err(Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "failed",
+                )))
+            },
+        );
+        for chunk in data.chunks(3) {
+            assert_eq!(writer.write(chunk)?, chunk.len());
+        }
+        let (written, res) = writer.finish();
+        assert_eq!(written, data);
+        assert!(matches!(res, Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::Other));
+
+        Ok(())
+    }
+
     #[test]
     fn clone_database() -> Result<(), Error> {
         let patterns = vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)];
 
         let db = wrapper::Database::new(patterns, ScanMode::BLOCK)?;
-        let _dbs: Vec<wrapper::Database> = (0..100).map(|_| db.clone()).collect();
+        let _dbs: Vec<wrapper::Database> =
+            (0..100).map(|_| db.deep_copy()).collect::<Result<_, _>>()?;
+        drop(db);
+        Ok(())
+    }
+
+    #[test]
+    fn stream_scan_with_history() -> Result<(), Error> {
+        let som = vec![Pattern::new(b"hel+o".to_vec(), Flag::SOM_LEFTMOST, Some(1))];
+        for mode in [ScanMode::SOM_LARGE, ScanMode::STREAM | ScanMode::BLOCK] {
+            assert!(matches!(
+                StreamingDatabase::with_mode(som.clone(), mode),
+                Err(Error::Hyperscan(HyperscanErrorCode::Invalid, _))
+            ));
+        }
+
+        // Patterns with `Flag::SOM_LEFTMOST` need a horizon in streaming mode
+        let db = StreamingDatabase::with_mode(som, ScanMode::STREAM | ScanMode::SOM_LARGE)?;
+        let mut scanner = StreamingScanner::new(&db)?;
+        let mut stream = scanner.open_stream()?.with_history(2);
+
+        let mut found = Vec::new();
+        for chunk in [&b"say hel"[..], b"lo there, helllllllo"] {
+            stream.scan_with_history(chunk, |id, from, to, _flags, history| {
+                let m = Match::new(id, from, to);
+                found.push(
+                    history
+                        .context(&m, 4, 2)
+                        .map(|c| (c.before.to_vec(), c.matched.to_vec(), c.after.to_vec())),
+                );
+                Scan::Continue
+            })?;
+        }
+        stream.close_with_history(|_id, _from, _to, _flags, _history| Scan::Continue)?;
+
+        assert_eq!(
+            found,
+            vec![
+                // The match began 3 bytes before the second chunk, more than the window of 2
+                None,
+                Some((b"re, ".to_vec(), b"helllllllo".to_vec(), Vec::new())),
+            ]
+        );
+
+        // A match that spans chunks is available while the window covers its start
+        let mut stream = scanner.open_stream()?.with_history(7);
+        let mut found = Vec::new();
+        for chunk in [&b"say hel"[..], b"lo there"] {
+            stream.scan_with_history(chunk, |id, from, to, _flags, history| {
+                let m = Match::new(id, from, to);
+                found.push(history.context(&m, 4, 2).map(|c| {
+                    (
+                        c.before.into_owned(),
+                        c.matched.into_owned(),
+                        c.after.into_owned(),
+                    )
+                }));
+                Scan::Continue
+            })?;
+        }
+        stream.discard()?;
+        assert_eq!(
+            found,
+            vec![Some((b"say ".to_vec(), b"hello".to_vec(), b" t".to_vec()))]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn stream_scan_tagged() -> Result<(), Error> {
+        let db = StreamingDatabase::new(vec![
+            Pattern::new(b"hello".to_vec(), Flag::default(), Some(1)),
+            Pattern::new(b"world$".to_vec(), Flag::default(), Some(2)),
+        ])?;
+        let mut scanner = StreamingScanner::new(&db)?;
+        let mut stream = scanner.open_stream()?.with_chunk_map(2);
+
+        let mut found = Vec::new();
+        let mut on_match = |id, _from, to, _flags, chunks: &ChunkMap| {
+            found.push((id, chunks.resolve_end(to)));
+            Scan::Continue
+        };
+        for (tag, chunk) in [
+            (100, &b"hel"[..]),
+            (101, b""),
+            (102, b"lo wor"),
+            (103, b"ld"),
+        ] {
+            stream.scan_tagged(tag, chunk, &mut on_match)?;
+        }
+        assert_eq!(
+            stream.chunk_map().resolve(2),
+            None,
+            "the first chunk is no longer kept"
+        );
+        assert_eq!(
+            stream.chunk_map().resolve(3),
+            Some(ChunkPosition {
+                index: 2,
+                tag: 102,
+                offset: 0
+            })
+        );
+        stream.close_with_chunk_map(&mut on_match)?;
+
+        assert_eq!(
+            found,
+            vec![
+                (
+                    1,
+                    Some(ChunkPosition {
+                        index: 2,
+                        tag: 102,
+                        offset: 2
+                    })
+                ),
+                (
+                    2,
+                    Some(ChunkPosition {
+                        index: 3,
+                        tag: 103,
+                        offset: 2
+                    })
+                ),
+            ]
+        );
+
+        // Without `with_chunk_map`, chunks scanned without a tag are counted but not recorded
+        let mut stream = scanner.open_stream()?;
+        stream.scan(b"hello", |_, _, _, _| Scan::Continue)?;
+        assert_eq!(stream.chunk_map().resolve(0), None);
+        stream.scan_tagged(7, b"world", |_, _, _, _, _| Scan::Continue)?;
+        assert_eq!(
+            stream.chunk_map().resolve(5),
+            Some(ChunkPosition {
+                index: 1,
+                tag: 7,
+                offset: 0
+            })
+        );
+        stream.discard()?;
+        Ok(())
+    }
+
+    #[test]
+    fn stream_line_index() -> Result<(), Error> {
+        let db = StreamingDatabase::new(vec![Pattern::new(
+            "wörld".as_bytes().to_vec(),
+            Flag::default(),
+            Some(1),
+        )])?;
+        let mut scanner = StreamingScanner::new(&db)?;
+        let mut stream = scanner.open_stream()?;
+
+        let mut lines = LineIndex::new();
+        let mut positions = Vec::new();
+        let data = "hello\nhéllo wörld\nwö".as_bytes();
+        for chunk in [&data[..9], &data[9..17], &data[17..]] {
+            lines.push(chunk);
+            stream.scan(chunk, |id, from, to, _flags| {
+                positions.push(lines.resolve_match(&Match::new(id, from, to), chunk));
+                Scan::Continue
+            })?;
+        }
+        stream.discard()?;
+
+        assert_eq!(lines.lines(), 3);
+        assert_eq!(
+            positions,
+            vec![Some(LinePosition {
+                line: 2,
+                column: 13,
+                char_column: Some(11),
+            })]
+        );
+
+        let last = &data[17..];
+        assert_eq!(
+            lines.resolve(6, last),
+            Some(LinePosition {
+                line: 2,
+                column: 1,
+                char_column: None,
+            })
+        );
+        assert_eq!(
+            lines.resolve(data.len() as u64, last),
+            Some(LinePosition {
+                line: 3,
+                column: 4,
+                char_column: Some(3),
+            })
+        );
+        assert_eq!(lines.resolve(data.len() as u64 + 1, last), None);
+
+        // A match ending on a multi-byte character resolves to the column of that character,
+        // including when the character starts in an earlier chunk
+        let db = BlockDatabase::new(vec![Pattern::new(
+            "é".as_bytes().to_vec(),
+            Flag::default(),
+            None,
+        )])?;
+        let data = "aé".as_bytes();
+        let mut lines = LineIndex::new();
+        lines.push(data);
+        let matches = db.find_all(data)?;
+        assert_eq!(matches, vec![Match::new(0, 0, 3)]);
+        let position = lines.resolve_match(&matches[0], data);
+        assert_eq!(
+            position.map(|p| (p.column, p.char_column)),
+            Some((3, Some(2)))
+        );
+
+        let mut lines = LineIndex::new();
+        lines.push(&data[..2]);
+        lines.push(&data[2..]);
+        assert_eq!(
+            lines.resolve(2, &data[2..]).and_then(|p| p.char_column),
+            Some(2)
+        );
+        assert_eq!(
+            lines.resolve(3, &data[2..]).and_then(|p| p.char_column),
+            Some(3)
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn stream_scan_archive() -> Result<(), Error> {
+        use std::io::{Cursor, Write};
+        use std::path::PathBuf;
+        use zip::unstable::write::FileOptionsExt;
+
+        let db = StreamingDatabase::new(vec![Pattern::new(
+            b"hello".to_vec(),
+            Flag::default(),
+            Some(1),
+        )])?;
+        let mut scanner = StreamingScanner::new(&db)?;
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("b.txt", options)
+            .map_err(std::io::Error::from)?;
+        zip.write_all(b"oh hello")?;
+        let zip = zip.finish().map_err(std::io::Error::from)?.into_inner();
+
+        let mut tar = tar::Builder::new(Vec::new());
+        for (path, data) in [("a.txt", &b"hello there"[..]), ("inner.zip", &zip)] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            tar.append_data(&mut header, path, data)?;
+        }
+        let tar = tar.into_inner()?;
+
+        let mut scan = |limits| {
+            let mut matches = Vec::new();
+            let scan = scanner
+                .scan_archive(Cursor::new(&tar), limits, |path, id, from, to, _flags| {
+                    matches.push((path.to_vec(), Match::new(id, from, to)));
+                    Scan::Continue
+                })
+                .transpose()?;
+            assert_eq!(scan.outcome, Scan::Continue);
+            assert!(scan.skipped.is_empty());
+            Ok::<_, Error>(matches)
+        };
+        let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            scan(ArchiveLimits::default())?,
+            vec![
+                (paths(&["a.txt"]), Match::new(1, 0, 5)),
+                (paths(&["inner.zip", "b.txt"]), Match::new(1, 0, 8)),
+            ]
+        );
+
+        // The stored zip archive is scanned as an ordinary entry when it is too deep to open
+        let limits = ArchiveLimits {
+            max_depth: 1,
+            ..ArchiveLimits::default()
+        };
+        let matches = scan(limits)?;
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[1].0, paths(&["inner.zip"]));
+
+        let limits = ArchiveLimits {
+            max_entry_size: 8,
+            ..ArchiveLimits::default()
+        };
+        let err = scan(limits).unwrap_err();
+        assert!(matches!(
+            &err,
+            Error::ArchiveLimit {
+                kind: ArchiveLimitKind::EntrySize,
+                limit: 8,
+                path,
+            } if path == &paths(&["a.txt"])
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Archive entry size limit of 8 bytes exceeded at a.txt"
+        );
+
+        let limits = ArchiveLimits {
+            max_total_size: 12,
+            ..ArchiveLimits::default()
+        };
+        assert!(matches!(
+            scan(limits),
+            Err(Error::ArchiveLimit {
+                kind: ArchiveLimitKind::TotalSize,
+                limit: 12,
+                ..
+            })
+        ));
+
+        // Nested zip archives held in memory count against the buffered size limit
+        let limits = ArchiveLimits {
+            max_buffered_size: 16,
+            ..ArchiveLimits::default()
+        };
+        assert!(matches!(
+            scan(limits),
+            Err(Error::ArchiveLimit {
+                kind: ArchiveLimitKind::BufferedSize,
+                limit: 16,
+                path,
+            }) if path == paths(&["inner.zip"])
+        ));
+
+        // The callback function can return any `CallbackResult`
+        let scan = scanner
+            .scan_archive(
+                Cursor::new(&tar),
+                ArchiveLimits::default(),
+                |path, _id, _from, _to, _flags| ControlFlow::Break(path.to_vec()),
+            )
+            .transpose()?;
+        assert_eq!(scan.outcome, ControlFlow::Break(paths(&["a.txt"])));
+        assert_eq!(scan.bytes, 11);
+
+        // Encrypted zip entries are skipped and reported
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file(
+            "secret.txt",
+            options.with_deprecated_encryption(b"password"),
+        )
+        .map_err(std::io::Error::from)?;
+        zip.write_all(b"hello secret")?;
+        zip.start_file("plain.txt", options)
+            .map_err(std::io::Error::from)?;
+        zip.write_all(b"hello")?;
+        let zip = zip.finish().map_err(std::io::Error::from)?.into_inner();
+
+        let mut matches = Vec::new();
+        let scan = scanner
+            .scan_archive(
+                Cursor::new(&zip),
+                ArchiveLimits::default(),
+                |path, id, from, to, _flags| {
+                    matches.push((path.to_vec(), Match::new(id, from, to)));
+                    Scan::Continue
+                },
+            )
+            .transpose()?;
+        assert_eq!(scan.skipped, vec![paths(&["secret.txt"])]);
+        assert_eq!(scan.bytes, 5);
+        assert_eq!(matches, vec![(paths(&["plain.txt"]), Match::new(1, 0, 5))]);
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn stream_scan_compressed_reader() -> Result<(), Error> {
+        use std::io::Write;
+
+        let db = StreamingDatabase::new(vec![
+            Pattern::new(b"hello".to_vec(), Flag::default(), Some(1)),
+            Pattern::new(b"world$".to_vec(), Flag::default(), Some(2)),
+        ])?;
+        let mut scanner = StreamingScanner::new(&db)?;
+        let data = b"hello hello world";
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(data)?;
+        let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bzip2.write_all(data)?;
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+        xz.write_all(data)?;
+        let inputs = [
+            (data.to_vec(), Compression::None),
+            (gzip.finish()?, Compression::Gzip),
+            (zstd::encode_all(&data[..], 0)?, Compression::Zstd),
+            (xz.finish()?, Compression::Xz),
+            (bzip2.finish()?, Compression::Bzip2),
+        ];
+
+        for (input, compression) in inputs {
+            assert_eq!(Compression::detect(&input), compression);
+
+            let mut matches = Vec::new();
+            let scan = scanner
+                .scan_compressed_reader(&input[..], 1024, |id, from, to, _flags| {
+                    matches.push(Match::new(id, from, to));
+                    Scan::Continue
+                })
+                .transpose()?;
+            assert_eq!(scan.outcome, Scan::Continue);
+            assert_eq!(scan.bytes, data.len() as u64);
+            assert_eq!(
+                matches,
+                vec![
+                    Match::new(1, 0, 5),
+                    Match::new(1, 0, 11),
+                    Match::new(2, 0, 17)
+                ]
+            );
+
+            let scan = scanner
+                .scan_compressed_reader(&input[..], 16, |_id, _from, _to, _flags| Scan::Continue);
+            assert!(matches!(scan.outcome, Err(Error::DecompressionLimit(16))));
+
+            // The callback function can return any `CallbackResult`
+            let scan = scanner
+                .scan_compressed_reader(&input[..], 1024, |id, _from, to, _flags| {
+                    if to > 5 {
+                        ControlFlow::Break(id)
+                    } else {
+                        ControlFlow::Continue(())
+                    }
+                })
+                .transpose()?;
+            assert_eq!(scan.outcome, ControlFlow::Break(1));
+        }
+
+        // Plain text that starts like a bzip2 header is scanned as is
+        let inputs = [
+            (
+                &b"BZhello world"[..],
+                vec![Match::new(1, 0, 8), Match::new(2, 0, 13)],
+            ),
+            (
+                b"BZh9 hello world",
+                vec![Match::new(1, 0, 10), Match::new(2, 0, 16)],
+            ),
+            (b"BZh", vec![]),
+        ];
+        for (input, expected) in inputs {
+            assert_eq!(Compression::detect(input), Compression::None);
+            let mut matches = Vec::new();
+            let scan = scanner
+                .scan_compressed_reader(input, 1024, |id, from, to, _flags| {
+                    matches.push(Match::new(id, from, to));
+                    Scan::Continue
+                })
+                .transpose()?;
+            assert_eq!(scan.bytes, input.len() as u64);
+            assert_eq!(matches, expected);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn block_scan_file() -> Result<(), Error> {
+        let db = BlockDatabase::new(vec![
+            Pattern::new(b"hello".to_vec(), Flag::default(), Some(1)),
+            Pattern::new(b"world$".to_vec(), Flag::default(), Some(2)),
+        ])?;
+
+        let path =
+            std::env::temp_dir().join(format!("vectorscan-rs-scan-file-{}", std::process::id()));
+        std::fs::write(&path, b"hello hello world")?;
+        let expected = vec![
+            Match::new(1, 0, 5),
+            Match::new(1, 0, 11),
+            Match::new(2, 0, 17),
+        ];
+
+        // SAFETY: the file is not modified while it is mapped
+        let mut matches = Vec::new();
+        let res = unsafe {
+            db.scan_file(&path, |id, from, to, _flags| {
+                matches.push(Match::new(id, from, to));
+                Scan::Continue
+            })
+        };
+        assert_eq!(res?, Scan::Continue);
+        assert_eq!(matches, expected);
+        let first = unsafe { db.scan_file(&path, |id, _from, _to, _flags| ControlFlow::Break(id)) };
+        assert_eq!(first?, ControlFlow::Break(1));
+
+        let mut matches = Vec::new();
+        let res = db.scan_file_by_reads(&path, |id, from, to, _flags| {
+            matches.push(Match::new(id, from, to));
+            Scan::Continue
+        });
+        assert_eq!(res?, Scan::Continue);
+        assert_eq!(matches, expected);
+
+        std::fs::write(&path, b"")?;
+        let empty = unsafe { db.scan_file(&path, |_id, _from, _to, _flags| Scan::Continue) };
+        assert_eq!(empty?, Scan::Continue);
+        let empty = db.scan_file_by_reads(&path, |_id, _from, _to, _flags| Scan::Continue);
+        assert_eq!(empty?, Scan::Continue);
+
+        // Files larger than the read buffer are scanned in windows, and truncating the file
+        // during the scan is reported as an error
+        let mut data = vec![b' '; 3 << 20];
+        data[..5].copy_from_slice(b"hello");
+        data[(2 << 20) - 2..(2 << 20) + 3].copy_from_slice(b"hello");
+        data.extend_from_slice(b"world");
+        std::fs::write(&path, &data)?;
+        let mut matches = Vec::new();
+        let res = db.scan_file_by_reads(&path, |id, from, to, _flags| {
+            matches.push(Match::new(id, from, to));
+            Scan::Continue
+        });
+        assert_eq!(res?, Scan::Continue);
+        let length = data.len() as u64;
+        assert_eq!(
+            matches,
+            vec![
+                Match::new(1, 0, 5),
+                Match::new(1, 0, (2 << 20) + 3),
+                Match::new(2, 0, length),
+            ]
+        );
+
+        let mut matches = 0;
+        let res = db.scan_file_by_reads(&path, |_id, _from, _to, _flags| -> Result<Scan, Error> {
+            matches += 1;
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(&path)?
+                .set_len(1 << 20)?;
+            Ok(Scan::Continue)
+        });
+        assert!(matches!(res, Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof));
+        assert_eq!(matches, 1);
+
+        std::fs::remove_file(&path)?;
+        assert!(matches!(
+            db.scan_file_by_reads(&path, |_id, _from, _to, _flags| Scan::Continue),
+            Err(Error::Io(_))
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn stream_scan_async() -> Result<(), Error> {
+        use futures_core::Stream;
+        use std::pin::Pin;
+        use std::task::{Context, Poll};
+
+        /// A stream of chunks from a vector
+        struct Chunks(std::vec::IntoIter<&'static [u8]>);
+
+        impl Stream for Chunks {
+            type Item = &'static [u8];
+
+            fn poll_next(
+                mut self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+            ) -> Poll<Option<Self::Item>> {
+                Poll::Ready(self.0.next())
+            }
+        }
+
+        async fn collect<S: Stream<Item = Result<Match, Error>> + Unpin>(
+            mut matches: S,
+        ) -> Result<Vec<Match>, Error> {
+            let mut result = Vec::new();
+            while let Some(m) =
+                std::future::poll_fn(|cx| Pin::new(&mut matches).poll_next(cx)).await
+            {
+                result.push(m?);
+            }
+            Ok(result)
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+        runtime.block_on(async {
+            let db = StreamingDatabase::new_async(vec![
+                Pattern::new(b"hello".to_vec(), Flag::default(), Some(1)),
+                Pattern::new(b"world$".to_vec(), Flag::default(), Some(2)),
+            ])
+            .await?;
+            let expected = vec![
+                Match::new(1, 0, 5),
+                Match::new(1, 0, 11),
+                Match::new(2, 0, 17),
+            ];
+
+            let data: &[u8] = b"hello hello world";
+            let stream = OwnedStreamScanner::new(db.clone())?;
+            let matches = ReaderMatches::with_buffer_size(stream, data, 4);
+            assert_eq!(collect(matches).await?, expected);
+
+            let chunks = Chunks(vec![&b"hel"[..], b"", b"lo hello wo", b"rld"].into_iter());
+            let matches = ChunkMatches::new(OwnedStreamScanner::new(db)?, chunks);
+            assert_eq!(collect(matches).await?, expected);
+
+            assert!(BlockDatabase::new_async(vec![Pattern::new(
+                b"hello(".to_vec(),
+                Flag::default(),
+                None
+            )])
+            .await
+            .is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn clone_block_database() -> Result<(), Error> {
+        let patterns = vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)];
+        let db = BlockDatabase::new(patterns)?;
+
+        let shared: Vec<BlockDatabase> = (0..100).map(|_| db.clone()).collect();
+        let copy = db.deep_copy()?;
+        assert_eq!(copy.size()?, db.size()?);
         drop(db);
+
+        for db in shared.iter().chain(std::iter::once(&copy)) {
+            let mut scanner = db.create_scanner()?;
+            assert_eq!(scanner.find_all(b"hello")?, vec![Match::new(0, 0, 5)]);
+        }
+
         Ok(())
     }
 
@@ -197,6 +1433,347 @@ mod tests {
         Ok(())
     }
 
+    /// Run `f`, expecting it to panic with the given message
+    fn expect_panic<R>(f: impl FnOnce() -> R, expected: &str) {
+        let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f))
+            .err()
+            .expect("Expected a panic");
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&expected));
+    }
+
+    #[test]
+    fn block_scan_callback_panic() -> Result<(), Error> {
+        let patterns = vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)];
+        let db = BlockDatabase::new(patterns)?;
+        let mut scanner = BlockScanner::new(&db)?;
+
+        let mut match_count = 0;
+        expect_panic(
+            || {
+                scanner.scan(b"hello hello", |_id, _from, _to, _flags| -> Scan {
+                    match_count += 1;
+                    panic!("block callback panic")
+                })
+            },
+            "block callback panic",
+        );
+        assert_eq!(match_count, 1); // The scan should have been terminated after the panic
+
+        // The scanner should still be usable afterward
+        let mut match_count = 0;
+        scanner.scan(b"hello hello", |_id, _from, _to, _flags| {
+            match_count += 1;
+            Scan::Continue
+        })?;
+        assert_eq!(match_count, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_scan_callback_panic() -> Result<(), Error> {
+        let patterns = vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)];
+        let db = StreamingDatabase::new(patterns)?;
+        let mut scanner = StreamingScanner::new(&db)?;
+
+        let mut stream = scanner.open_stream()?;
+        let mut match_count = 0;
+        expect_panic(
+            || {
+                stream.scan(b"hello hello", |_id, _from, _to, _flags| -> Scan {
+                    match_count += 1;
+                    panic!("stream callback panic")
+                })
+            },
+            "stream callback panic",
+        );
+        assert_eq!(match_count, 1);
+        stream.close_with(|_id, _from, _to, _flags| Scan::Continue)?;
+
+        // The scanner should still be usable afterward with a new stream
+        let mut stream = scanner.open_stream()?;
+        let mut match_count = 0;
+        stream.scan(b"hello hello", |_id, _from, _to, _flags| {
+            match_count += 1;
+            Scan::Continue
+        })?;
+        assert_eq!(match_count, 2);
+        stream.close_with(|_id, _from, _to, _flags| Scan::Continue)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_close_callback_panic() -> Result<(), Error> {
+        // A pattern anchored at the end of data only matches when the stream is closed
+        let patterns = vec![Pattern::new(b"hello$".to_vec(), Flag::default(), None)];
+        let db = StreamingDatabase::new(patterns)?;
+        let mut scanner = StreamingScanner::new(&db)?;
+
+        let mut stream = scanner.open_stream()?;
+        stream.scan(b"hello", |_id, _from, _to, _flags| -> Scan {
+            panic!("no match expected before close")
+        })?;
+
+        expect_panic(
+            || {
+                stream.close_with(|_id, _from, _to, _flags| -> Scan {
+                    panic!("close callback panic")
+                })
+            },
+            "close callback panic",
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_scan_fallible_callback() -> Result<(), Error> {
+        let patterns = vec![
+            Pattern::new(b"hello".to_vec(), Flag::default(), Some(0)),
+            Pattern::new(b"world".to_vec(), Flag::default(), Some(1)),
+        ];
+        let db = BlockDatabase::new(patterns)?;
+        let mut scanner = BlockScanner::new(&db)?;
+
+        // An error from the callback stops the scan and is returned
+        let mut match_count = 0;
+        let res = scanner.scan(b"hello world hello", |id, _from, _to, _flags| {
+            match_count += 1;
+            if id == 1 {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::BrokenPipe,
+                    "closed",
+                ))
+            } else {
+                Ok(Scan::Continue)
+            }
+        });
+        let err = res.expect_err("Expected error but got success");
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+        assert_eq!(match_count, 2);
+
+        // Without errors, the scan completes as usual
+        let res = scanner.scan(b"hello hello", |_id, _from, _to, _flags| {
+            Ok::<_, std::io::Error>(Scan::Continue)
+        });
+        assert!(matches!(res, Ok(Scan::Continue)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_scan_control_flow_callback() -> Result<(), Error> {
+        let patterns = vec![
+            Pattern::new(b"hello".to_vec(), Flag::default(), Some(0)),
+            Pattern::new(b"world".to_vec(), Flag::default(), Some(1)),
+        ];
+        let db = BlockDatabase::new(patterns)?;
+        let mut scanner = BlockScanner::new(&db)?;
+
+        // Find the first match and return its id
+        let first = scanner.scan(b"say world, then hello", |id, _from, to, _flags| {
+            ControlFlow::Break((id, to))
+        })?;
+        assert_eq!(first, ControlFlow::Break((1, 9)));
+
+        let first = scanner.scan(b"nothing here", |id, _from, to, _flags| {
+            ControlFlow::Break((id, to))
+        })?;
+        assert_eq!(first, ControlFlow::Continue(()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_control_flow_callback() -> Result<(), Error> {
+        let patterns = vec![Pattern::new(b"hello$".to_vec(), Flag::default(), Some(3))];
+        let db = StreamingDatabase::new(patterns)?;
+        let mut scanner = StreamingScanner::new(&db)?;
+
+        let mut stream = scanner.open_stream()?;
+        let found = stream.scan(b"hel", |id, _from, _to, _flags| ControlFlow::Break(id))?;
+        assert_eq!(found, ControlFlow::Continue(()));
+        let found = stream.scan(b"lo", |id, _from, _to, _flags| ControlFlow::Break(id))?;
+        assert_eq!(found, ControlFlow::Continue(()));
+        let found = stream.close_with(|id, _from, _to, _flags| ControlFlow::Break(id))?;
+        assert_eq!(found, ControlFlow::Break(3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_find_all() -> Result<(), Error> {
+        let patterns = vec![
+            Pattern::new(b"hello".to_vec(), Flag::SOM_LEFTMOST, Some(0)),
+            Pattern::new(b"w[o0]rld".to_vec(), Flag::SOM_LEFTMOST, Some(1)),
+        ];
+        let db = BlockDatabase::new(patterns)?;
+        let mut scanner = BlockScanner::new(&db)?;
+
+        let data = b"hello w0rld hello";
+        let matches = scanner.find_all(data)?;
+        assert_eq!(
+            matches,
+            vec![
+                Match::new(0, 0, 5),
+                Match::new(1, 6, 11),
+                Match::new(0, 12, 17),
+            ]
+        );
+        assert_eq!(matches[1].range(), 6..11);
+        assert_eq!(matches[1].slice(data), b"w0rld");
+        assert_eq!(Range::<usize>::from(matches[2]), 12..17);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_find_all() -> Result<(), Error> {
+        let patterns = vec![
+            Pattern::new(b"hello".to_vec(), Flag::default(), Some(0)),
+            Pattern::new(b"world$".to_vec(), Flag::default(), Some(1)),
+        ];
+        let db = StreamingDatabase::new(patterns)?;
+        let mut scanner = StreamingScanner::new(&db)?;
+
+        let mut stream = scanner.open_stream()?;
+        assert_eq!(stream.find_all(b"hel")?, vec![]);
+        assert_eq!(stream.find_all(b"lo wor")?, vec![Match::new(0, 0, 5)]);
+        assert_eq!(stream.find_all(b"ld")?, vec![]);
+        assert_eq!(stream.close_find_all()?, vec![Match::new(1, 0, 11)]);
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "requires a multi-gigabyte input"]
+    fn block_scan_too_large() -> Result<(), Error> {
+        let patterns = vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)];
+        let db = BlockDatabase::new(patterns)?;
+        let mut scanner = BlockScanner::new(&db)?;
+
+        let data = vec![0u8; u32::MAX as usize + 1];
+        let res = scanner.scan(&data, |_id, _from, _to, _flags| Scan::Continue);
+        assert!(matches!(res, Err(Error::InputTooLarge(len)) if len == data.len()));
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "requires a multi-gigabyte input"]
+    fn stream_scan_larger_than_u32() -> Result<(), Error> {
+        let patterns = vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)];
+        let db = StreamingDatabase::new(patterns)?;
+        let mut scanner = StreamingScanner::new(&db)?;
+
+        // Place one match straddling the 4 GiB boundary, and another past it
+        let boundary = u32::MAX as usize;
+        let mut data = vec![0u8; boundary + 16];
+        data[boundary - 2..boundary + 3].copy_from_slice(b"hello");
+        data[boundary + 8..boundary + 13].copy_from_slice(b"hello");
+
+        let mut stream = scanner.open_stream()?;
+        let matches = stream.find_all(&data)?;
+        assert_eq!(
+            matches,
+            vec![
+                Match::new(0, 0, boundary as u64 + 3),
+                Match::new(0, 0, boundary as u64 + 13),
+            ]
+        );
+        assert_eq!(stream.close_find_all()?, vec![]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_drop_and_discard() -> Result<(), Error> {
+        let patterns = vec![Pattern::new(b"hello$".to_vec(), Flag::default(), None)];
+        let db = StreamingDatabase::new(patterns)?;
+        let mut scanner = StreamingScanner::new(&db)?;
+
+        // Discarding a stream skips matches at the end of the stream
+        let mut stream = scanner.open_stream()?;
+        assert_eq!(stream.find_all(b"hello")?, vec![]);
+        stream.discard()?;
+
+        // As does dropping a stream
+        let mut stream = scanner.open_stream()?;
+        assert_eq!(stream.find_all(b"hello")?, vec![]);
+        drop(stream);
+
+        // Closing a stream explicitly reports them
+        let mut stream = scanner.open_stream()?;
+        assert_eq!(stream.find_all(b"hello")?, vec![]);
+        assert_eq!(stream.close_find_all()?, vec![Match::new(0, 0, 5)]);
+
+        Ok(())
+    }
+
+    /// Streams that are dropped without being closed should be freed; when built with Address
+    /// Sanitizer, a leak here is reported by LeakSanitizer.
+    #[cfg(feature = "asan")]
+    #[test]
+    fn stream_drop_no_leak() -> Result<(), Error> {
+        let patterns = vec![Pattern::new(
+            b"hello.*world".to_vec(),
+            Flag::default(),
+            None,
+        )];
+        let db = StreamingDatabase::new(patterns)?;
+        let mut scanner = StreamingScanner::new(&db)?;
+
+        for _ in 0..1000 {
+            let mut stream = scanner.open_stream()?;
+            stream.scan(b"hello", |_id, _from, _to, _flags| Scan::Continue)?;
+        }
+
+        Ok(())
+    }
+
+    fn assert_send_static<T: Send + 'static>(v: T) -> T {
+        v
+    }
+
+    #[test]
+    fn owned_block_scanner() -> Result<(), Error> {
+        let patterns = vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)];
+        let db = BlockDatabase::new(patterns)?;
+        let mut scanner = assert_send_static(OwnedBlockScanner::new(db.clone())?);
+        assert!(Scratch::new(&db)?.is_valid_for(scanner.database()));
+        drop(db);
+
+        let handle = std::thread::spawn(move || scanner.find_all(b"hello hello"));
+        let matches = handle.join().unwrap()?;
+        assert_eq!(matches, vec![Match::new(0, 0, 5), Match::new(0, 0, 11)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn owned_stream_scanner() -> Result<(), Error> {
+        let patterns = vec![Pattern::new(b"hello".to_vec(), Flag::default(), None)];
+        let db = StreamingDatabase::new(patterns)?;
+        let scanner = OwnedStreamingScanner::new(db)?;
+
+        let mut stream1 = assert_send_static(scanner.open_stream()?);
+        let mut stream2 = assert_send_static(scanner.into_stream()?);
+        assert_eq!(stream1.find_all(b"hel")?, vec![]);
+        assert_eq!(stream2.find_all(b"hello")?, vec![Match::new(0, 0, 5)]);
+
+        // Streams can continue on another thread
+        let handle = std::thread::spawn(move || -> Result<_, Error> {
+            let matches = stream1.find_all(b"lo")?;
+            stream1.close_with(|_id, _from, _to, _flags| Scan::Continue)?;
+            Ok(matches)
+        });
+        assert_eq!(handle.join().unwrap()?, vec![Match::new(0, 0, 5)]);
+        stream2.discard()?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_pattern_compilation_errors() -> Result<(), Error> {
         // Test invalid regex syntax
@@ -227,4 +1804,75 @@ mod tests {
 
         Ok(())
     }
+
+    #[cfg(feature = "chimera")]
+    #[test]
+    fn chimera_capture_groups() -> Result<(), Error> {
+        let patterns = vec![ChimeraPattern::new(
+            br"(\w+)@(\w+)\.com".to_vec(),
+            ChimeraFlag::default(),
+            Some(7),
+        )];
+        let db = ChimeraDatabase::new(patterns, ChimeraMode::GROUPS)?;
+        let mut scanner = db.create_scanner()?;
+
+        let mut matches = Vec::new();
+        scanner.scan(
+            b"mail bob@example.com now",
+            |id, from, to, _flags, captures| {
+                let captures: Vec<_> = captures.iter().map(|c| c.range()).collect();
+                matches.push((id, from, to, captures));
+                ChimeraScan::Continue
+            },
+            |_event, _id| ChimeraScan::Continue,
+        )?;
+        assert_eq!(
+            matches,
+            vec![(7, 5, 20, vec![Some(5..20), Some(5..8), Some(9..16)])]
+        );
+
+        // The callbacks can return any `CallbackResult`
+        let res = scanner.scan(
+            b"mail bob@example.com now",
+            |_id, _from, _to, _flags, captures| ControlFlow::Break(captures[1].range()),
+            |_event, _id| ControlFlow::Continue(()),
+        )?;
+        assert_eq!(res, ControlFlow::Break(Some(5..8)));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "chimera")]
+    #[test]
+    fn chimera_match_limit() -> Result<(), Error> {
+        let patterns = vec![ChimeraPattern::new(
+            b"(a|b)*c".to_vec(),
+            ChimeraFlag::default(),
+            None,
+        )];
+        let limits = ChimeraMatchLimits {
+            match_limit: 1,
+            match_limit_recursion: 0,
+        };
+        let db = ChimeraDatabase::with_match_limits(patterns, ChimeraMode::NOGROUPS, limits)?;
+        let mut scanner = db.create_scanner()?;
+
+        let mut matches = Vec::new();
+        let mut errors = Vec::new();
+        scanner.scan(
+            b"abababababababc",
+            |id, from, to, _flags, _captures| {
+                matches.push((id, from, to));
+                ChimeraScan::Continue
+            },
+            |event, id| {
+                errors.push((event, id));
+                ChimeraScan::Continue
+            },
+        )?;
+        assert_eq!(matches, vec![]);
+        assert_eq!(errors, vec![(ChimeraErrorEvent::MatchLimit, 0)]);
+
+        Ok(())
+    }
 }
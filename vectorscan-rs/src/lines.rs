@@ -0,0 +1,146 @@
+use super::Match;
+
+// -------------------------------------------------------------------------------------------------
+// Line Index
+// -------------------------------------------------------------------------------------------------
+
+/// The position of a byte within lines of text
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LinePosition {
+    /// The 1-based line number
+    pub line: u64,
+
+    /// The 1-based column, counted in bytes
+    pub column: u64,
+
+    /// The 1-based column, counted in UTF-8 characters, if it could be determined
+    ///
+    /// This is the column of the character containing the byte, which may start before it. Every
+    /// byte that is not a UTF-8 continuation byte is counted as a character, so invalid UTF-8 does
+    /// not prevent counting.
+    pub char_column: Option<u64>,
+}
+
+/// An index of the lines in scanned data, built incrementally as the data is scanned, for
+/// resolving match offsets to line and column numbers
+///
+/// Push each chunk of data into the index before scanning it, whether in block mode or as part
+/// of a stream, then resolve the offsets reported by the scan of that chunk, passing the chunk
+/// again.
+///
+/// Only the positions of newlines are kept, so line numbers and byte columns can be resolved for
+/// any offset. Character columns need the bytes of the line, so they are resolved only for
+/// offsets within the most recently pushed chunk. These include the offsets of every match
+/// reported by the scan of that chunk, even if its line started in an earlier chunk.
+#[derive(Clone, Debug, Default)]
+pub struct LineIndex {
+    /// The offset of the start of each line after the first, with the number of characters
+    /// before it
+    line_starts: Vec<(u64, u64)>,
+
+    /// The total number of bytes pushed
+    len: u64,
+
+    /// The total number of characters pushed
+    chars: u64,
+
+    /// The offset of the start of the most recently pushed chunk
+    chunk_start: u64,
+
+    /// The number of characters before the most recently pushed chunk
+    chunk_chars: u64,
+}
+
+impl LineIndex {
+    /// Create an empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the next chunk of data to the index
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.chunk_start = self.len;
+        self.chunk_chars = self.chars;
+        let mut line_start = 0;
+        for (i, _) in chunk.iter().enumerate().filter(|(_, &b)| b == b'\n') {
+            self.chars += count_chars(&chunk[line_start..=i]);
+            line_start = i + 1;
+            self.line_starts
+                .push((self.len + line_start as u64, self.chars));
+        }
+        self.chars += count_chars(&chunk[line_start..]);
+        self.len += chunk.len() as u64;
+    }
+
+    /// Get the total number of bytes pushed
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Check whether no bytes have been pushed
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the number of lines seen so far, including an unterminated last line
+    pub fn lines(&self) -> u64 {
+        self.line_starts.len() as u64 + 1
+    }
+
+    /// Resolve the given offset to a line and column
+    ///
+    /// `chunk` must be the most recently pushed chunk; the character column is resolved if the
+    /// offset lies within it or at its end. Returns `None` if the offset is beyond the data
+    /// pushed so far.
+    pub fn resolve(&self, offset: u64, chunk: &[u8]) -> Option<LinePosition> {
+        if offset > self.len {
+            return None;
+        }
+        let line = self
+            .line_starts
+            .partition_point(|&(start, _)| start <= offset);
+        let (line_start, line_chars) = match line {
+            0 => (0, 0),
+            line => self.line_starts[line - 1],
+        };
+
+        let char_column = offset
+            .checked_sub(self.chunk_start)
+            .filter(|_| self.chunk_start + chunk.len() as u64 == self.len)
+            .map(|within| {
+                let within = within as usize;
+                let chars = self.chunk_chars + count_chars(&chunk[..within]) - line_chars;
+                // A continuation byte belongs to a character that has already been counted
+                match chunk.get(within) {
+                    Some(&b) if is_continuation(b) => chars.max(1),
+                    _ => chars + 1,
+                }
+            });
+
+        Some(LinePosition {
+            line: line as u64 + 1,
+            column: offset - line_start + 1,
+            char_column,
+        })
+    }
+
+    /// Resolve the position of the last byte of the given match
+    ///
+    /// Without `Flag::SOM_LEFTMOST`, the end of a match is the only position Vectorscan reports;
+    /// use `resolve(m.start, chunk)` for the start of matches of such patterns. Empty matches
+    /// resolve to the position of their end offset. See [`LineIndex::resolve`] for details.
+    pub fn resolve_match(&self, m: &Match, chunk: &[u8]) -> Option<LinePosition> {
+        self.resolve(m.end.saturating_sub(1).max(m.start), chunk)
+    }
+}
+
+/// Count the UTF-8 characters in the given bytes, counting every byte that is not a continuation
+/// byte
+fn count_chars(bytes: &[u8]) -> u64 {
+    bytes.iter().filter(|&&b| !is_continuation(b)).count() as u64
+}
+
+/// Check whether the given byte is a UTF-8 continuation byte
+fn is_continuation(b: u8) -> bool {
+    (b as i8) < -0x40
+}
@@ -0,0 +1,215 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use super::{BlockDatabase, CallbackResult, Error, Scan, Scratch, Stream};
+
+/// The size of the windows in which files too large for block mode are scanned; the file's
+/// length is checked before each window
+const WINDOW_SIZE: usize = 1 << 30;
+
+/// The size of the buffer that `BlockDatabase::scan_file_by_reads` reads into; smaller files are
+/// read whole and scanned in block mode
+const READ_BUFFER_SIZE: usize = 1 << 20;
+
+// -------------------------------------------------------------------------------------------------
+// Memory-Mapped File Scanning
+// -------------------------------------------------------------------------------------------------
+
+impl BlockDatabase {
+    /// Scan the file at the given path by memory-mapping it, using the given callback function
+    ///
+    /// Files of up to `u32::MAX` bytes are scanned in block mode, using a per-thread scratch space
+    /// as with [`BlockDatabase::scan`]. Larger files are scanned in streaming mode over the
+    /// mapping, using a streaming database compiled once from the same patterns; match offsets are
+    /// relative to the start of the file either way. Empty files are scanned without being mapped.
+    ///
+    /// See [`BlockScanner::scan`](crate::BlockScanner::scan) for details of the callback function.
+    ///
+    /// Errors opening or mapping the file are returned as `Error::Io`. For files that may change
+    /// during the scan, use [`BlockDatabase::scan_file_by_reads`] instead.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be truncated or modified, by this or any other process, while it is
+    /// scanned. On most platforms, reading a part of the mapping beyond the end of a truncated
+    /// file raises `SIGBUS`, which terminates the process, and modifications may be partially
+    /// visible to the scan. As a best-effort diagnostic only, the file's length is compared with
+    /// the mapping after the scan and, in streaming mode, before each 1 GiB window, and a shorter
+    /// file is reported as an `Error::Io` of kind `UnexpectedEof`.
+    pub unsafe fn scan_file<P, F, R>(&self, path: P, mut on_match: F) -> R::Output
+    where
+        P: AsRef<Path>,
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        let mut stopped = None;
+        let res = self.scan_mapped_file(path.as_ref(), |id, from, to, flags| {
+            let res = on_match(id, from, to, flags);
+            if res.should_continue() {
+                Scan::Continue
+            } else {
+                stopped = Some(res);
+                Scan::Terminate
+            }
+        });
+        R::into_output(stopped, res)
+    }
+
+    /// Scan the file at the given path by reading it, using the given callback function
+    ///
+    /// Unlike [`BlockDatabase::scan_file`], this is safe to use with files that are modified
+    /// during the scan. The file is read using positioned reads, such as `pread` on Unix. Files
+    /// of up to 1 MiB are read whole and scanned in block mode, using a per-thread scratch space
+    /// as with [`BlockDatabase::scan`]. Larger files are read and scanned in streaming mode in
+    /// windows of 1 MiB, using a streaming database compiled once from the same patterns; match
+    /// offsets are relative to the start of the file either way.
+    ///
+    /// Only the length of the file when the scan starts is scanned. If the file is truncated
+    /// during the scan, an `Error::Io` of kind `UnexpectedEof` is returned; other modifications
+    /// may be partially visible to the scan. Errors opening or reading the file are returned as
+    /// `Error::Io`.
+    ///
+    /// See [`BlockScanner::scan`](crate::BlockScanner::scan) for details of the callback function.
+    pub fn scan_file_by_reads<P, F, R>(&self, path: P, mut on_match: F) -> R::Output
+    where
+        P: AsRef<Path>,
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        let mut stopped = None;
+        let res = self.scan_read_file(path.as_ref(), |id, from, to, flags| {
+            let res = on_match(id, from, to, flags);
+            if res.should_continue() {
+                Scan::Continue
+            } else {
+                stopped = Some(res);
+                Scan::Terminate
+            }
+        });
+        R::into_output(stopped, res)
+    }
+
+    fn scan_read_file<F>(&self, path: &Path, mut on_match: F) -> Result<Scan, Error>
+    where
+        F: FnMut(u32, u64, u64, u32) -> Scan,
+    {
+        let file = File::open(path)?;
+        let length = file.metadata()?.len();
+
+        if length <= READ_BUFFER_SIZE as u64 {
+            let mut buf = vec![0; length as usize];
+            read_exact_at(&file, &mut buf, 0)?;
+            return self.scan(&buf, on_match);
+        }
+
+        let db = self.streaming_twin()?;
+        let mut scratch = Scratch::new(&db)?;
+        let mut stream = Stream::new(&db)?;
+        let mut buf = vec![0; READ_BUFFER_SIZE];
+        let mut offset = 0;
+        while offset < length {
+            let window = &mut buf[..(length - offset).min(READ_BUFFER_SIZE as u64) as usize];
+            read_exact_at(&file, window, offset)?;
+            offset += window.len() as u64;
+            if stream.scan(&mut scratch, window, &mut on_match)? == Scan::Terminate {
+                return Ok(Scan::Terminate);
+            }
+        }
+        stream.close_with(&mut scratch, &mut on_match)
+    }
+
+    fn scan_mapped_file<F>(&self, path: &Path, mut on_match: F) -> Result<Scan, Error>
+    where
+        F: FnMut(u32, u64, u64, u32) -> Scan,
+    {
+        let file = File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            // Mapping an empty file fails on some platforms
+            return self.scan(&[], on_match);
+        }
+
+        // SAFETY: the mapping is only read, and only while the file is open. The caller of
+        // `scan_file` guarantees that the file is not modified during the scan.
+        let map = unsafe { Mmap::map(&file)? };
+
+        let res = if u32::try_from(map.len()).is_ok() {
+            self.scan(&map, &mut on_match)?
+        } else {
+            let db = self.streaming_twin()?;
+            let mut scratch = Scratch::new(&db)?;
+            let mut stream = Stream::new(&db)?;
+            let mut res = Scan::Continue;
+            for window in map.chunks(WINDOW_SIZE) {
+                check_length(&file, map.len())?;
+                res = stream.scan(&mut scratch, window, &mut on_match)?;
+                if res == Scan::Terminate {
+                    break;
+                }
+            }
+            if res == Scan::Continue {
+                res = stream.close_with(&mut scratch, &mut on_match)?;
+            }
+            res
+        };
+
+        check_length(&file, map.len())?;
+        Ok(res)
+    }
+}
+
+/// Check that the file is still at least as long as its mapping
+fn check_length(file: &File, length: usize) -> Result<(), Error> {
+    if file.metadata()?.len() < length as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "file was truncated during the scan",
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Fill the buffer from the file, starting at the given offset
+///
+/// Returns an error of kind `UnexpectedEof` if the file ends before the buffer is full.
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> Result<(), Error> {
+    while !buf.is_empty() {
+        match read_at(file, buf, offset) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "file was truncated during the scan",
+                )
+                .into())
+            }
+            Ok(n) => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::windows::fs::FileExt::seek_read(file, buf, offset)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn read_at(mut file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    file.seek(SeekFrom::Start(offset))?;
+    file.read(buf)
+}
@@ -1,8 +1,23 @@
 use foreign_types::ForeignType;
+use std::any::Any;
+use std::cell::RefCell;
 use std::ffi::{c_int, c_uint, c_ulonglong, c_void};
+use std::io::{self, BufRead, BufReader, Read};
 use std::mem::MaybeUninit;
+use std::ops::{ControlFlow, Range};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+#[cfg(any(feature = "rayon", feature = "mmap"))]
+use std::sync::OnceLock;
 use vectorscan_rs_sys as hs;
 
+use super::chunks::ChunkMap;
+use super::error::report_free_failure;
+use super::history::{History, HistoryBuffer};
+#[cfg(any(feature = "rayon", feature = "mmap"))]
+use super::Flag;
 use super::{wrapper, AsResult, Error, HyperscanErrorCode, Pattern, ScanMode};
 
 // -------------------------------------------------------------------------------------------------
@@ -12,26 +27,367 @@ use super::{wrapper, AsResult, Error, HyperscanErrorCode, Pattern, ScanMode};
 /// The result returned by a scan callback
 ///
 /// This is also called a "match event handler" in the Vectorscan C API documentation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Scan {
     Continue,
     Terminate,
 }
 
+/// A value that can be returned by a scan callback
+///
+/// This is implemented for three types, which determine what the scanning method returns:
+///
+/// - `Scan`: the scan returns `Result<Scan, Error>`, with `Scan::Terminate` if the callback
+///   requested that scanning stop
+///
+/// - `Result<Scan, E>`: the scan returns `Result<Scan, E>`; an error from the callback stops the
+///   scan and is returned as is, and errors from Vectorscan are converted using `E: From<Error>`
+///
+/// - `ControlFlow<B>`: the scan returns `Result<ControlFlow<B>, Error>`; a `ControlFlow::Break`
+///   from the callback stops the scan and its value is returned
+pub trait CallbackResult: Sized {
+    /// The type returned by a scan whose callback returns this type
+    type Output;
+
+    /// Should scanning continue after the callback has returned this value?
+    fn should_continue(&self) -> bool;
+
+    /// Produce the result of a scan
+    ///
+    /// `stopped` is the value returned by the callback that stopped the scan, if any.
+    /// `res` is the result of the underlying Vectorscan call.
+    fn into_output(stopped: Option<Self>, res: Result<Scan, Error>) -> Self::Output;
+}
+
+impl CallbackResult for Scan {
+    type Output = Result<Scan, Error>;
+
+    fn should_continue(&self) -> bool {
+        matches!(self, Scan::Continue)
+    }
+
+    fn into_output(_stopped: Option<Self>, res: Result<Scan, Error>) -> Self::Output {
+        res
+    }
+}
+
+impl<E: From<Error>> CallbackResult for Result<Scan, E> {
+    type Output = Result<Scan, E>;
+
+    fn should_continue(&self) -> bool {
+        matches!(self, Ok(Scan::Continue))
+    }
+
+    fn into_output(stopped: Option<Self>, res: Result<Scan, Error>) -> Self::Output {
+        match stopped {
+            Some(Err(e)) => Err(e),
+            _ => Ok(res?),
+        }
+    }
+}
+
+impl<B> CallbackResult for ControlFlow<B> {
+    type Output = Result<ControlFlow<B>, Error>;
+
+    fn should_continue(&self) -> bool {
+        self.is_continue()
+    }
+
+    fn into_output(stopped: Option<Self>, res: Result<Scan, Error>) -> Self::Output {
+        match (stopped, res?) {
+            (Some(stopped), _) => Ok(stopped),
+            (None, Scan::Continue) => Ok(ControlFlow::Continue(())),
+            // The scan was terminated without the callback having returned `Break`, as happens
+            // when scanning a stream that was terminated earlier; there is no value to return
+            (None, Scan::Terminate) => Err(Error::Hyperscan(
+                HyperscanErrorCode::ScanTerminated,
+                hs::HS_SCAN_TERMINATED,
+            )),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// Match
+// -------------------------------------------------------------------------------------------------
+
+/// A match reported by a scan
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Match {
+    /// The ID of the expression that matched
+    pub id: u32,
+
+    /// The offset of the start byte of the match
+    ///
+    /// This is only meaningful for patterns compiled with `Flag::SOM_LEFTMOST`; otherwise it is 0.
+    pub start: u64,
+
+    /// The offset of the byte after the end byte of the match
+    pub end: u64,
+}
+
+impl Match {
+    /// Create a match from the arguments given to a scan callback
+    pub fn new(id: u32, from: u64, to: u64) -> Self {
+        Self {
+            id,
+            start: from,
+            end: to,
+        }
+    }
+
+    /// The offsets of the match
+    pub fn range(&self) -> Range<u64> {
+        self.start..self.end
+    }
+
+    /// Get the bytes of the match from the data that was scanned in block mode
+    ///
+    /// # Panics
+    /// If the match does not lie within `data`.
+    pub fn slice<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[self.start as usize..self.end as usize]
+    }
+}
+
+impl From<Match> for Range<u64> {
+    fn from(m: Match) -> Self {
+        m.range()
+    }
+}
+
+impl From<Match> for Range<usize> {
+    fn from(m: Match) -> Self {
+        m.start as usize..m.end as usize
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// Scratch
+// -------------------------------------------------------------------------------------------------
+
+mod sealed {
+    use super::wrapper;
+
+    pub trait Sealed {
+        /// The underlying compiled database
+        fn raw(&self) -> &wrapper::Database;
+
+        /// A unique identifier for the underlying compiled database, shared by clones
+        fn id(&self) -> u64;
+
+        /// A clone of this database behind a trait object
+        fn boxed(&self) -> Box<dyn super::Database + Send + Sync>;
+    }
+}
+
+/// A compiled Vectorscan database that scratch space can be allocated for
+///
+/// This trait is sealed: it is implemented only by [`BlockDatabase`] and [`StreamingDatabase`].
+pub trait Database: sealed::Sealed {}
+
+/// Get a fresh identifier for a newly compiled or copied database
+fn next_database_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Scratch space used by Vectorscan while scanning
+///
+/// A scratch space is allocated using `hs_alloc_scratch` for one or more databases, and can only
+/// be used with those databases (or their clones); scanners check this when they are created.
+/// Scanning requires exclusive access to the scratch space, which is expressed by the scanning
+/// methods taking `&mut self`.
+#[derive(Debug)]
+pub struct Scratch {
+    inner: wrapper::Scratch,
+    /// The identifiers of the databases this scratch space has been allocated for
+    database_ids: Vec<u64>,
+}
+
+impl Scratch {
+    /// Allocate a new scratch space for the given database using `hs_alloc_scratch`
+    pub fn new<D: Database + ?Sized>(db: &D) -> Result<Self, Error> {
+        Ok(Self {
+            inner: wrapper::Scratch::new(db.raw())?,
+            database_ids: vec![db.id()],
+        })
+    }
+
+    /// Allocate a new scratch space that can be used with any of the given databases
+    ///
+    /// The databases may be a mix of block and streaming databases. Returns `Error::Hyperscan`
+    /// with `HyperscanErrorCode::Invalid` if no databases are given.
+    pub fn for_databases(dbs: &[&dyn Database]) -> Result<Self, Error> {
+        let Some((first, rest)) = dbs.split_first() else {
+            return Err(Error::Hyperscan(
+                HyperscanErrorCode::Invalid,
+                hs::HS_INVALID,
+            ));
+        };
+        rest.iter()
+            .try_fold(Self::new(*first)?, |scratch, db| scratch.add_database(*db))
+    }
+
+    /// Grow this scratch space using `hs_alloc_scratch` so that it can also be used with the
+    /// given database
+    ///
+    /// The scratch space may be reallocated, so it is consumed; on failure, it is freed.
+    pub fn add_database<D: Database + ?Sized>(mut self, db: &D) -> Result<Self, Error> {
+        if self.is_valid_for(db) {
+            return Ok(self);
+        }
+        self.inner = self.inner.grow(db.raw())?;
+        self.database_ids.push(db.id());
+        Ok(self)
+    }
+
+    /// Create a copy of this scratch space using `hs_clone_scratch`
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        Ok(Self {
+            inner: self.inner.try_clone()?,
+            database_ids: self.database_ids.clone(),
+        })
+    }
+
+    /// Check whether this scratch space can be used with the given database
+    pub fn is_valid_for<D: Database + ?Sized>(&self, db: &D) -> bool {
+        self.database_ids.contains(&db.id())
+    }
+
+    /// Get the size in bytes of the scratch space
+    pub fn size(&self) -> Result<usize, Error> {
+        self.inner.size()
+    }
+
+    /// Return `Error::ScratchMismatch` if this scratch space cannot be used with the given database
+    fn check<D: Database + ?Sized>(&self, db: &D) -> Result<(), Error> {
+        if self.is_valid_for(db) {
+            Ok(())
+        } else {
+            Err(Error::ScratchMismatch)
+        }
+    }
+}
+
+/// Creates a copy of the scratch space using `hs_clone_scratch`.
+///
+/// Panics if the copy fails; use `Scratch::try_clone` to handle that case.
+impl Clone for Scratch {
+    fn clone(&self) -> Self {
+        self.try_clone().expect("hs_clone_scratch failed")
+    }
+}
+
+/// The number of databases that a per-thread scratch space is grown for before it is replaced
+const THREAD_SCRATCH_MAX_DATABASES: usize = 4;
+
+thread_local! {
+    /// A per-thread scratch space for scanning directly with a database, allocated lazily and
+    /// grown for each database it is used with, up to `THREAD_SCRATCH_MAX_DATABASES`
+    static THREAD_SCRATCH: RefCell<Option<Scratch>> = const { RefCell::new(None) };
+}
+
+/// Call `f` with this thread's cached scratch space, made usable with the given database
+///
+/// Once the cached scratch space has been grown for `THREAD_SCRATCH_MAX_DATABASES` databases, it is
+/// replaced by one allocated for just the given database, so that it doesn't grow without bound
+/// as databases come and go.
+///
+/// If the cached scratch space is already in use, as when a match callback scans with a database
+/// directly, or if the thread is exiting, a temporary scratch space is allocated instead.
+fn with_thread_scratch<D, F, T>(db: &D, f: F) -> T
+where
+    D: Database,
+    F: FnOnce(Result<&mut Scratch, Error>) -> T,
+{
+    let mut f = Some(f);
+    let res = THREAD_SCRATCH.try_with(|cached| {
+        let mut cached = cached.try_borrow_mut().ok()?;
+        let scratch = match cached.take() {
+            Some(scratch) if scratch.is_valid_for(db) => Ok(scratch),
+            Some(scratch) if scratch.database_ids.len() < THREAD_SCRATCH_MAX_DATABASES => {
+                scratch.add_database(db)
+            }
+            _ => Scratch::new(db),
+        };
+        let f = f.take()?;
+        Some(match scratch {
+            Ok(scratch) => f(Ok(cached.insert(scratch))),
+            Err(e) => f(Err(e)),
+        })
+    });
+    match (res, f) {
+        (Ok(Some(res)), _) => res,
+        (_, Some(f)) => match Scratch::new(db) {
+            Ok(mut scratch) => f(Ok(&mut scratch)),
+            Err(e) => f(Err(e)),
+        },
+        (_, None) => unreachable!("callback is taken only when it is called"),
+    }
+}
+
 // -------------------------------------------------------------------------------------------------
 // Block Database
 // -------------------------------------------------------------------------------------------------
 
 /// A database that supports Vectorscan's block-based matching APIs
+///
+/// Cloning is cheap: clones share the underlying compiled database through reference counting.
+/// Use `deep_copy` to create an independent copy of the database.
+///
+/// With the `rayon` or `mmap` feature, the database keeps the patterns it was compiled from,
+/// which are shared by its clones and deep copies.
 #[derive(Clone, Debug)]
 pub struct BlockDatabase {
-    inner: wrapper::Database,
+    inner: Arc<wrapper::Database>,
+    id: u64,
+    #[cfg(any(feature = "rayon", feature = "mmap"))]
+    patterns: Arc<[Pattern]>,
+    #[cfg(any(feature = "rayon", feature = "mmap"))]
+    derived: Arc<Derived>,
+}
+
+/// Values derived from the patterns of a block database, computed on first use and shared by its
+/// clones and deep copies
+#[cfg(any(feature = "rayon", feature = "mmap"))]
+#[derive(Debug, Default)]
+pub(crate) struct Derived {
+    /// The maximum width of a match of any pattern, if the patterns can be scanned in chunks
+    #[cfg(feature = "rayon")]
+    pub(crate) max_match_width: OnceLock<Option<u32>>,
+    /// A streaming database compiled from the same patterns
+    streaming_twin: OnceLock<StreamingDatabase>,
 }
 
 impl BlockDatabase {
     /// Create a new database with the given patterns
     pub fn new(patterns: Vec<Pattern>) -> Result<Self, Error> {
+        #[cfg(any(feature = "rayon", feature = "mmap"))]
+        let kept: Arc<[Pattern]> = patterns.as_slice().into();
         let inner = wrapper::Database::new(patterns, ScanMode::BLOCK)?;
-        Ok(Self { inner })
+        Ok(Self {
+            inner: Arc::new(inner),
+            id: next_database_id(),
+            #[cfg(any(feature = "rayon", feature = "mmap"))]
+            patterns: kept,
+            #[cfg(any(feature = "rayon", feature = "mmap"))]
+            derived: Arc::default(),
+        })
+    }
+
+    /// Create an independent copy of this database, via serialization and deserialization
+    ///
+    /// Scratch space allocated for this database cannot be used with the copy.
+    pub fn deep_copy(&self) -> Result<Self, Error> {
+        Ok(Self {
+            inner: Arc::new(self.inner.deep_copy()?),
+            id: next_database_id(),
+            #[cfg(any(feature = "rayon", feature = "mmap"))]
+            patterns: self.patterns.clone(),
+            #[cfg(any(feature = "rayon", feature = "mmap"))]
+            derived: self.derived.clone(),
+        })
     }
 
     /// Create a new scanner from this database
@@ -39,12 +395,112 @@ impl BlockDatabase {
         BlockScanner::new(self)
     }
 
+    /// Scan the input using a per-thread scratch space and the given callback function
+    ///
+    /// This can be called from any thread without creating a scanner: a scratch space is
+    /// allocated for each thread the first time it scans, using `hs_alloc_scratch`, and is reused
+    /// by later scans on that thread, including scans with other databases.
+    ///
+    /// See [`BlockScanner::scan`] for details.
+    pub fn scan<F, R>(&self, data: &[u8], on_match: F) -> R::Output
+    where
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        with_thread_scratch(self, |scratch| match scratch {
+            Ok(scratch) => block_scan(&self.inner, scratch, data, on_match),
+            Err(e) => Context::new(on_match).finish(Err(e)),
+        })
+    }
+
+    /// Scan the input using a per-thread scratch space, returning all of the matches
+    pub fn find_all(&self, data: &[u8]) -> Result<Vec<Match>, Error> {
+        let mut matches = Vec::new();
+        self.scan(data, collect_matches(&mut matches))?;
+        Ok(matches)
+    }
+
+    /// Scan the input using the given scratch space and callback function
+    ///
+    /// See [`BlockScanner::scan`] for details. If the scratch space was not allocated for this
+    /// database, `Error::ScratchMismatch` is returned.
+    pub fn scan_with<F, R>(&self, scratch: &mut Scratch, data: &[u8], on_match: F) -> R::Output
+    where
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        if let Err(e) = scratch.check(self) {
+            return Context::new(on_match).finish(Err(e));
+        }
+        block_scan(&self.inner, scratch, data, on_match)
+    }
+
     /// Get the size in bytes of the database
     pub fn size(&self) -> Result<usize, Error> {
         self.inner.size()
     }
+
+    /// Get the patterns this database was compiled from
+    ///
+    /// This is only available with the `rayon` or `mmap` feature, which need the patterns to
+    /// compile a streaming database or analyze them for chunked scanning.
+    #[cfg(any(feature = "rayon", feature = "mmap"))]
+    pub fn patterns(&self) -> &[Pattern] {
+        &self.patterns
+    }
+
+    /// Get the values derived from the patterns of this database
+    #[cfg(feature = "rayon")]
+    pub(crate) fn derived(&self) -> &Derived {
+        &self.derived
+    }
+
+    /// Get a streaming database compiled from the same patterns, for scanning inputs that are too
+    /// large for block mode
+    ///
+    /// The streaming database is compiled on first use, and shared by clones of this database.
+    /// Start of match offsets of `SOM_LEFTMOST` patterns are tracked over the whole stream.
+    #[cfg(any(feature = "rayon", feature = "mmap"))]
+    pub(crate) fn streaming_twin(&self) -> Result<StreamingDatabase, Error> {
+        if let Some(db) = self.derived.streaming_twin.get() {
+            return Ok(db.clone());
+        }
+        // N.B. concurrent first uses may each compile a database; only one of them is kept
+        let db = self.compile_streaming_twin()?;
+        Ok(self.derived.streaming_twin.get_or_init(|| db).clone())
+    }
+
+    #[cfg(any(feature = "rayon", feature = "mmap"))]
+    fn compile_streaming_twin(&self) -> Result<StreamingDatabase, Error> {
+        let mode = if self
+            .patterns
+            .iter()
+            .any(|p| p.flags().contains(Flag::SOM_LEFTMOST))
+        {
+            ScanMode::STREAM | ScanMode::SOM_LARGE
+        } else {
+            ScanMode::STREAM
+        };
+        StreamingDatabase::with_mode(self.patterns.to_vec(), mode)
+    }
+}
+
+impl sealed::Sealed for BlockDatabase {
+    fn raw(&self) -> &wrapper::Database {
+        &self.inner
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn boxed(&self) -> Box<dyn Database + Send + Sync> {
+        Box::new(self.clone())
+    }
 }
 
+impl Database for BlockDatabase {}
+
 // -------------------------------------------------------------------------------------------------
 // Block Scanner
 // -------------------------------------------------------------------------------------------------
@@ -52,7 +508,7 @@ impl BlockDatabase {
 /// A scanner that supports Vectorscan's block-based matching APIs
 #[derive(Clone, Debug)]
 pub struct BlockScanner<'db> {
-    scratch: wrapper::Scratch,
+    scratch: Scratch,
     db: &'db BlockDatabase,
 }
 
@@ -61,13 +517,38 @@ impl<'db> BlockScanner<'db> {
     pub fn new(db: &'db BlockDatabase) -> Result<Self, Error> {
         Ok(Self {
             db,
-            scratch: wrapper::Scratch::new(&db.inner)?,
+            scratch: Scratch::new(db)?,
+        })
+    }
+
+    /// Create a new scanner with the given database, using an existing scratch space
+    ///
+    /// Returns `Error::ScratchMismatch` if the scratch space was not allocated for the database.
+    pub fn with_scratch(db: &'db BlockDatabase, scratch: Scratch) -> Result<Self, Error> {
+        scratch.check(db)?;
+        Ok(Self { db, scratch })
+    }
+
+    /// Consume this scanner, returning its scratch space
+    pub fn into_scratch(self) -> Scratch {
+        self.scratch
+    }
+
+    /// Create a copy of this scanner, with its own scratch space
+    ///
+    /// Unlike `clone`, this returns an error instead of panicking if the scratch space cannot be
+    /// copied.
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        Ok(Self {
+            db: self.db,
+            scratch: self.scratch.try_clone()?,
         })
     }
 
     /// Scan the input using the given callback function
     ///
-    /// The callback function takes 4 arguments and returns a `Scan` value.
+    /// The callback function takes 4 arguments and returns a [`CallbackResult`] value, such as
+    /// `Scan`, `Result<Scan, E>`, or `ControlFlow<B>`, which determines what this method returns.
     /// The 4 arguments:
     ///
     /// - id: u32     The ID of the expression that matched
@@ -75,36 +556,67 @@ impl<'db> BlockScanner<'db> {
     /// - to: u64     The offset of the byte after the end byte of the match
     /// - flags: u32  Unused; "provided for future use"
     ///
+    /// Vectorscan's block mode cannot scan inputs larger than `u32::MAX` bytes; for such inputs,
+    /// `Error::InputTooLarge` is returned. Use streaming mode to scan them instead.
+    ///
     /// For more detail, see the Hyperscan documentation:
     ///
     /// - [`hs_scan`](https://intel.github.io/hyperscan/dev-reference/api_files.html#c.hs_scan)
     /// - [`match_event_handler`](https://intel.github.io/hyperscan/dev-reference/api_files.html#c.match_event_handler)
-    pub fn scan<F>(&mut self, data: &[u8], on_match: F) -> Result<Scan, Error>
+    pub fn scan<F, R>(&mut self, data: &[u8], on_match: F) -> R::Output
     where
-        F: FnMut(u32, u64, u64, u32) -> Scan,
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
     {
-        let mut context = Context { on_match };
+        block_scan(&self.db.inner, &mut self.scratch, data, on_match)
+    }
 
-        let res = unsafe {
-            hs::hs_scan(
-                self.db.inner.as_ptr(),
-                data.as_ptr() as *const _,
-                data.len() as u32,
-                0,
-                self.scratch.as_ptr(),
-                Some(on_match_trampoline::<F>),
-                &mut context as *mut _ as *mut c_void,
-            )
-            .ok()
-        };
+    /// Scan the input, returning all of the matches
+    pub fn find_all(&mut self, data: &[u8]) -> Result<Vec<Match>, Error> {
+        let mut matches = Vec::new();
+        self.scan(data, collect_matches(&mut matches))?;
+        Ok(matches)
+    }
+}
 
-        match res {
-            Ok(_) => Ok(Scan::Continue),
-            Err(err) => match err {
-                Error::Hyperscan(HyperscanErrorCode::ScanTerminated, _) => Ok(Scan::Terminate),
-                err => Err(err),
-            },
-        }
+/// Scan the input in block mode using the given database and scratch
+fn block_scan<F, R>(
+    db: &wrapper::Database,
+    scratch: &mut Scratch,
+    data: &[u8],
+    on_match: F,
+) -> R::Output
+where
+    F: FnMut(u32, u64, u64, u32) -> R,
+    R: CallbackResult,
+{
+    let mut context = Context::new(on_match);
+
+    let Ok(length) = u32::try_from(data.len()) else {
+        return context.finish(Err(Error::InputTooLarge(data.len())));
+    };
+
+    let res = unsafe {
+        hs::hs_scan(
+            db.as_ptr(),
+            data.as_ptr() as *const _,
+            length,
+            0,
+            scratch.inner.as_ptr(),
+            Some(on_match_trampoline::<F, R>),
+            &mut context as *mut _ as *mut c_void,
+        )
+        .ok()
+    };
+
+    context.finish(res)
+}
+
+/// Create a scan callback that appends each match to the given vector
+fn collect_matches(matches: &mut Vec<Match>) -> impl FnMut(u32, u64, u64, u32) -> Scan + '_ {
+    |id, from, to, _flags| {
+        matches.push(Match::new(id, from, to));
+        Scan::Continue
     }
 }
 
@@ -113,16 +625,50 @@ impl<'db> BlockScanner<'db> {
 // -------------------------------------------------------------------------------------------------
 
 /// A database that supports Vectorscan's streaming matching APIs
+///
+/// Cloning is cheap: clones share the underlying compiled database through reference counting.
+/// Use `deep_copy` to create an independent copy of the database.
 #[derive(Clone, Debug)]
 pub struct StreamingDatabase {
-    inner: wrapper::Database,
+    inner: Arc<wrapper::Database>,
+    id: u64,
 }
 
 impl StreamingDatabase {
     /// Create a new database with the given patterns
     pub fn new(patterns: Vec<Pattern>) -> Result<Self, Error> {
-        let inner = wrapper::Database::new(patterns, ScanMode::STREAM)?;
-        Ok(Self { inner })
+        Self::with_mode(patterns, ScanMode::STREAM)
+    }
+
+    /// Create a new database with the given patterns and mode flags
+    ///
+    /// The mode must include `ScanMode::STREAM`, and may include one of the `ScanMode::SOM_*`
+    /// horizons, which patterns with `Flag::SOM_LEFTMOST` require in streaming mode. Returns
+    /// `Error::Hyperscan` with `HyperscanErrorCode::Invalid` if the mode does not include
+    /// `ScanMode::STREAM`, or includes `ScanMode::BLOCK` or `ScanMode::VECTORED`.
+    pub fn with_mode(patterns: Vec<Pattern>, mode: ScanMode) -> Result<Self, Error> {
+        if !mode.contains(ScanMode::STREAM) || mode.intersects(ScanMode::BLOCK | ScanMode::VECTORED)
+        {
+            return Err(Error::Hyperscan(
+                HyperscanErrorCode::Invalid,
+                hs::HS_INVALID,
+            ));
+        }
+        let inner = wrapper::Database::new(patterns, mode)?;
+        Ok(Self {
+            inner: Arc::new(inner),
+            id: next_database_id(),
+        })
+    }
+
+    /// Create an independent copy of this database, via serialization and deserialization
+    ///
+    /// Scratch space allocated for this database cannot be used with the copy.
+    pub fn deep_copy(&self) -> Result<Self, Error> {
+        Ok(Self {
+            inner: Arc::new(self.inner.deep_copy()?),
+            id: next_database_id(),
+        })
     }
 
     /// Create a new scanner from this database
@@ -141,26 +687,176 @@ impl StreamingDatabase {
     }
 }
 
+impl sealed::Sealed for StreamingDatabase {
+    fn raw(&self) -> &wrapper::Database {
+        &self.inner
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn boxed(&self) -> Box<dyn Database + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+impl Database for StreamingDatabase {}
+
 // -------------------------------------------------------------------------------------------------
 // Stream
 // -------------------------------------------------------------------------------------------------
+/// A stream opened against a `StreamingDatabase`
+///
+/// A stream holds a handle to its database, and is not tied to any particular scanner or scratch
+/// space: each call to `scan` or `close_with` takes the scratch space to use, which may be any
+/// scratch space allocated for the same database. Streams can be moved between threads, so a
+/// stream can be scanned on whichever thread has its data, using that thread's scratch space.
+///
+/// The stream is freed when this object is dropped.
 #[derive(Debug)]
 pub struct Stream {
     inner: *mut hs::hs_stream_t,
+    db: StreamingDatabase,
 }
 
 impl Stream {
-    fn new(database: &StreamingDatabase) -> Result<Self, Error> {
+    /// Open a new stream for the given database using `hs_open_stream`
+    pub fn new(db: &StreamingDatabase) -> Result<Self, Error> {
         let mut inner = MaybeUninit::zeroed();
         let flags = 0;
         unsafe {
-            hs::hs_open_stream(database.inner.as_ptr(), flags, inner.as_mut_ptr())
+            hs::hs_open_stream(db.inner.as_ptr(), flags, inner.as_mut_ptr())
                 .ok()
                 .map(|()| Self {
                     inner: inner.assume_init(),
+                    db: db.clone(),
                 })
         }
     }
+
+    /// Get the database of this stream
+    pub fn database(&self) -> &StreamingDatabase {
+        &self.db
+    }
+
+    /// Scan the next chunk of input using the given scratch space and callback function
+    ///
+    /// See [`StreamScanner::scan`] for details. If the scratch space was not allocated for this
+    /// stream's database, `Error::ScratchMismatch` is returned.
+    pub fn scan<F, R>(&mut self, scratch: &mut Scratch, data: &[u8], on_match: F) -> R::Output
+    where
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        let mut context = Context::new(on_match);
+
+        if let Err(e) = scratch.check(&self.db) {
+            return context.finish(Err(e));
+        }
+
+        // `hs_scan_stream` takes a 32-bit length, so larger inputs are scanned in several calls.
+        // Offsets are relative to the start of the stream, so they remain correct.
+        let mut remaining = data;
+        let res = loop {
+            let length = remaining.len().min(u32::MAX as usize);
+            let res = unsafe {
+                hs::hs_scan_stream(
+                    self.inner,
+                    remaining.as_ptr() as *const _,
+                    length as u32,
+                    0,
+                    scratch.inner.as_ptr(),
+                    Some(on_match_trampoline::<F, R>),
+                    &mut context as *mut _ as *mut c_void,
+                )
+                .ok()
+            };
+            remaining = &remaining[length..];
+            if res.is_err() || remaining.is_empty() {
+                break res;
+            }
+        };
+
+        context.finish(res)
+    }
+
+    /// Scan the next chunk of input, returning all of the matches that end within it
+    pub fn find_all(&mut self, scratch: &mut Scratch, data: &[u8]) -> Result<Vec<Match>, Error> {
+        let mut matches = Vec::new();
+        self.scan(scratch, data, collect_matches(&mut matches))?;
+        Ok(matches)
+    }
+
+    /// Close the stream using `hs_close_stream`, using the given scratch space and callback
+    /// function
+    ///
+    /// See [`StreamScanner::close_with`] for details. If the scratch space was not allocated for
+    /// this stream's database, `Error::ScratchMismatch` is returned and the stream is discarded.
+    pub fn close_with<F, R>(mut self, scratch: &mut Scratch, on_match: F) -> R::Output
+    where
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        let mut context = Context::new(on_match);
+
+        if let Err(e) = scratch.check(&self.db) {
+            return context.finish(Err(e));
+        }
+
+        let res = unsafe {
+            hs::hs_close_stream(
+                self.take(),
+                scratch.inner.as_ptr(),
+                Some(on_match_trampoline::<F, R>),
+                &mut context as *mut _ as *mut c_void,
+            )
+            .ok()
+        };
+
+        context.finish(res)
+    }
+
+    /// Close the stream, returning all of the matches at the end of the stream
+    pub fn close_find_all(self, scratch: &mut Scratch) -> Result<Vec<Match>, Error> {
+        let mut matches = Vec::new();
+        self.close_with(scratch, collect_matches(&mut matches))?;
+        Ok(matches)
+    }
+
+    /// Close the stream using `hs_close_stream`, without processing matches at the end of the
+    /// stream.
+    ///
+    /// This is equivalent to dropping this object, but reports any error from Vectorscan.
+    pub fn discard(mut self) -> Result<(), Error> {
+        self.free()
+    }
+
+    /// Take the underlying stream, leaving this object empty so that it is not closed on drop
+    fn take(&mut self) -> *mut hs::hs_stream_t {
+        std::mem::replace(&mut self.inner, ptr::null_mut())
+    }
+
+    /// Close the underlying stream without processing matches at the end of the stream
+    fn free(&mut self) -> Result<(), Error> {
+        unsafe { hs::hs_close_stream(self.take(), ptr::null_mut(), None, ptr::null_mut()).ok() }
+    }
+}
+
+// SAFETY: a Vectorscan stream has no affinity to the thread that opened it, and is only ever
+// accessed through `&mut self`.
+unsafe impl Send for Stream {}
+
+/// Frees the stream if it was not explicitly closed, without processing matches at the end of the
+/// stream.
+impl Drop for Stream {
+    fn drop(&mut self) {
+        if !self.inner.is_null() {
+            if let Err(Error::Hyperscan(_, code)) = self.free() {
+                report_free_failure("hs_close_stream", code);
+            }
+        }
+    }
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -170,14 +866,18 @@ impl Stream {
 /// A scanner that supports Vectorscan's streaming matching APIs
 #[derive(Clone, Debug)]
 pub struct StreamingScanner<'db> {
-    scratch: wrapper::Scratch,
+    scratch: Scratch,
     db: &'db StreamingDatabase,
 }
 
+/// A stream opened by a [`StreamingScanner`], which has exclusive use of the scanner's scratch
+/// space until it is closed or dropped
 #[derive(Debug)]
 pub struct StreamScanner<'ss> {
-    scanner: &'ss StreamingScanner<'ss>,
+    scratch: &'ss mut Scratch,
     stream: Stream,
+    history: HistoryBuffer,
+    chunks: ChunkMap,
 }
 
 impl<'db> StreamingScanner<'db> {
@@ -185,50 +885,152 @@ impl<'db> StreamingScanner<'db> {
     pub fn new(db: &'db StreamingDatabase) -> Result<Self, Error> {
         Ok(Self {
             db,
-            scratch: wrapper::Scratch::new(&db.inner)?,
+            scratch: Scratch::new(db)?,
+        })
+    }
+
+    /// Create a new scanner with the given database, using an existing scratch space
+    ///
+    /// Returns `Error::ScratchMismatch` if the scratch space was not allocated for the database.
+    pub fn with_scratch(db: &'db StreamingDatabase, scratch: Scratch) -> Result<Self, Error> {
+        scratch.check(db)?;
+        Ok(Self { db, scratch })
+    }
+
+    /// Consume this scanner, returning its scratch space
+    pub fn into_scratch(self) -> Scratch {
+        self.scratch
+    }
+
+    /// Create a copy of this scanner, with its own scratch space
+    ///
+    /// Unlike `clone`, this returns an error instead of panicking if the scratch space cannot be
+    /// copied.
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        Ok(Self {
+            db: self.db,
+            scratch: self.scratch.try_clone()?,
         })
     }
 
     /// Open a new `Stream` object using `hs_open_stream`
-    pub fn open_stream(&self) -> Result<StreamScanner<'_>, Error> {
+    ///
+    /// The stream borrows this scanner's scratch space mutably, so only one stream can be open
+    /// at a time. To scan several streams concurrently, use a scanner for each one.
+    pub fn open_stream(&mut self) -> Result<StreamScanner<'_>, Error> {
         let stream = Stream::new(self.db)?;
         Ok(StreamScanner {
             stream,
-            scanner: self,
+            scratch: &mut self.scratch,
+            history: HistoryBuffer::default(),
+            chunks: ChunkMap::default(),
         })
     }
+
+    /// Scan everything read from the given reader as a single stream, using a buffer of
+    /// `DEFAULT_READ_BUFFER_SIZE` bytes
+    ///
+    /// See [`StreamingScanner::scan_buf_reader`] for details.
+    pub fn scan_reader<T, F, R>(&mut self, reader: T, on_match: F) -> ReaderScan<R::Output>
+    where
+        T: Read,
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        self.scan_reader_with_buffer_size(reader, DEFAULT_READ_BUFFER_SIZE, on_match)
+    }
+
+    /// Scan everything read from the given reader as a single stream, using a buffer of the given
+    /// size
+    ///
+    /// A buffer size of 0 is treated as 1. See [`StreamingScanner::scan_buf_reader`] for details.
+    pub fn scan_reader_with_buffer_size<T, F, R>(
+        &mut self,
+        reader: T,
+        buffer_size: usize,
+        on_match: F,
+    ) -> ReaderScan<R::Output>
+    where
+        T: Read,
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        self.scan_buf_reader(
+            BufReader::with_capacity(buffer_size.max(1), reader),
+            on_match,
+        )
+    }
+
+    /// Scan everything read from the given buffered reader as a single stream
+    ///
+    /// A stream is opened, each buffer of input is passed to `hs_scan_stream` as it is read, and
+    /// the stream is closed at the end of the input, so that matches at the end of the stream are
+    /// reported. The callback function is as for [`StreamScanner::scan`], and the outcome of the
+    /// scan is what that method would return.
+    ///
+    /// Returns the outcome of the scan along with the number of bytes scanned. If the callback
+    /// function stops the scan, reading stops after the buffer containing the match, which is
+    /// included in the count, and the stream is discarded. Errors from the reader are returned as
+    /// `Error::Io`, along with the number of bytes scanned before the error.
+    pub fn scan_buf_reader<T, F, R>(&mut self, reader: T, mut on_match: F) -> ReaderScan<R::Output>
+    where
+        T: BufRead,
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        let mut stopped = None;
+        let mut bytes = 0;
+        let res = self.open_stream().and_then(|stream| {
+            scan_buf_reader(stream, reader, &mut bytes, |id, from, to, flags| {
+                let res = on_match(id, from, to, flags);
+                if res.should_continue() {
+                    Scan::Continue
+                } else {
+                    stopped = Some(res);
+                    Scan::Terminate
+                }
+            })
+        });
+        ReaderScan {
+            bytes,
+            outcome: R::into_output(stopped, res),
+        }
+    }
 }
 
 impl<'ss> StreamScanner<'ss> {
     /// Close the given `Stream` object using `hs_close_stream`.
-    pub fn close<F>(self, on_match: F) -> Result<Scan, Error>
+    ///
+    /// This is an alias for `close_with`.
+    #[deprecated(
+        note = "use `close_with`, or `discard` to close without processing matches at the end of the stream"
+    )]
+    pub fn close<F, R>(self, on_match: F) -> R::Output
     where
-        F: FnMut(u32, u64, u64, u32) -> Scan,
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
     {
-        let mut context = Context { on_match };
-
-        let res = unsafe {
-            hs::hs_close_stream(
-                self.stream.inner,
-                self.scanner.scratch.as_ptr(),
-                Some(on_match_trampoline::<F>),
-                &mut context as *mut _ as *mut c_void,
-            )
-            .ok()
-        };
+        self.close_with(on_match)
+    }
 
-        match res {
-            Ok(_) => Ok(Scan::Continue),
-            Err(err) => match err {
-                Error::Hyperscan(HyperscanErrorCode::ScanTerminated, _) => Ok(Scan::Terminate),
-                err => Err(err),
-            },
-        }
+    /// Close the given `Stream` object using `hs_close_stream`.
+    ///
+    /// The callback function is invoked for any matches at the end of the stream, as with `scan`.
+    ///
+    /// To close the stream without processing matches at the end of the stream, use `discard`, or
+    /// simply drop this object.
+    pub fn close_with<F, R>(self, on_match: F) -> R::Output
+    where
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        self.stream.close_with(self.scratch, on_match)
     }
 
     /// Scan the input using the given callback function
     ///
-    /// The callback function takes 4 arguments and returns a `Scan` value.
+    /// The callback function takes 4 arguments and returns a [`CallbackResult`] value, such as
+    /// `Scan`, `Result<Scan, E>`, or `ControlFlow<B>`, which determines what this method returns.
     /// The 4 arguments:
     ///
     /// - id: u32     The ID of the expression that matched
@@ -236,37 +1038,399 @@ impl<'ss> StreamScanner<'ss> {
     /// - to: u64     The offset of the byte after the end byte of the match
     /// - flags: u32  Unused; "provided for future use"
     ///
+    /// Inputs larger than `u32::MAX` bytes are scanned using several calls to `hs_scan_stream`.
+    ///
     /// For more detail, see the Hyperscan documentation:
     ///
     /// - [`hs_scan_stream`](https://intel.github.io/hyperscan/dev-reference/api_files.html#c.hs_scan_stream)
     /// - [`match_event_handler`](https://intel.github.io/hyperscan/dev-reference/api_files.html#c.match_event_handler)
-    pub fn scan<F>(&mut self, data: &[u8], on_match: F) -> Result<Scan, Error>
+    pub fn scan<F, R>(&mut self, data: &[u8], on_match: F) -> R::Output
     where
-        F: FnMut(u32, u64, u64, u32) -> Scan,
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
     {
-        let mut context = Context { on_match };
+        self.chunks.push(None, data);
+        let res = self.stream.scan(self.scratch, data, on_match);
+        self.history.push(data);
+        res
+    }
 
-        let res = unsafe {
-            hs::hs_scan_stream(
-                self.stream.inner,
-                data.as_ptr() as *const _,
-                data.len() as u32,
-                0,
-                self.scanner.scratch.as_ptr(),
-                Some(on_match_trampoline::<F>),
-                &mut context as *mut _ as *mut c_void,
-            )
-            .ok()
-        };
+    /// Keep up to `window` bytes of earlier chunks of the stream, so that the callback functions
+    /// of `scan_with_history` and `close_with_history` can retrieve bytes of matches that began
+    /// in earlier chunks
+    ///
+    /// Only bytes of chunks scanned after this is called are kept. By default, no bytes of
+    /// earlier chunks are kept.
+    pub fn with_history(mut self, window: usize) -> Self {
+        self.history.set_window(window);
+        self
+    }
 
-        match res {
-            Ok(_) => Ok(Scan::Continue),
-            Err(err) => match err {
-                Error::Hyperscan(HyperscanErrorCode::ScanTerminated, _) => Ok(Scan::Terminate),
-                err => Err(err),
-            },
+    /// Scan the input using the given callback function, which is also given a [`History`] of
+    /// the most recent bytes of the stream
+    ///
+    /// The history covers this chunk and up to the window of bytes set by `with_history` before
+    /// it. Otherwise, this is as [`StreamScanner::scan`].
+    pub fn scan_with_history<F, R>(&mut self, data: &[u8], mut on_match: F) -> R::Output
+    where
+        F: FnMut(u32, u64, u64, u32, &History<'_>) -> R,
+        R: CallbackResult,
+    {
+        self.chunks.push(None, data);
+        let history = self.history.view(data);
+        let res = self.stream.scan(self.scratch, data, |id, from, to, flags| {
+            on_match(id, from, to, flags, &history)
+        });
+        self.history.push(data);
+        res
+    }
+
+    /// Keep the boundaries of up to `max_chunks` of the most recent chunks of the stream in its
+    /// [`ChunkMap`], rather than only the most recent chunk
+    ///
+    /// This also records the chunks scanned by `scan` and `scan_with_history`, which otherwise
+    /// only advance the chunk indices.
+    pub fn with_chunk_map(mut self, max_chunks: usize) -> Self {
+        self.chunks.set_max_chunks(max_chunks);
+        self
+    }
+
+    /// Get the map of the most recent chunks scanned, for resolving match offsets to offsets
+    /// within the chunks
+    pub fn chunk_map(&self) -> &ChunkMap {
+        &self.chunks
+    }
+
+    /// Scan the input as a chunk with the given tag, such as a packet number or timestamp, using
+    /// the given callback function, which is also given the stream's [`ChunkMap`]
+    ///
+    /// The end offset of each match can be resolved to an offset within this chunk using
+    /// [`ChunkMap::resolve_end`]. Otherwise, this is as [`StreamScanner::scan`].
+    pub fn scan_tagged<F, R>(&mut self, tag: u64, data: &[u8], mut on_match: F) -> R::Output
+    where
+        F: FnMut(u32, u64, u64, u32, &ChunkMap) -> R,
+        R: CallbackResult,
+    {
+        self.chunks.push(Some(tag), data);
+        let chunks = &self.chunks;
+        let res = self.stream.scan(self.scratch, data, |id, from, to, flags| {
+            on_match(id, from, to, flags, chunks)
+        });
+        self.history.push(data);
+        res
+    }
+
+    /// Close the stream using the given callback function, which is also given the stream's
+    /// [`ChunkMap`]
+    ///
+    /// Matches at the end of the stream end in the last non-empty chunk. Otherwise, this is as
+    /// [`StreamScanner::close_with`].
+    pub fn close_with_chunk_map<F, R>(self, mut on_match: F) -> R::Output
+    where
+        F: FnMut(u32, u64, u64, u32, &ChunkMap) -> R,
+        R: CallbackResult,
+    {
+        let chunks = &self.chunks;
+        self.stream.close_with(self.scratch, |id, from, to, flags| {
+            on_match(id, from, to, flags, chunks)
+        })
+    }
+
+    /// Close the stream using the given callback function, which is also given a [`History`] of
+    /// the last bytes of the stream, up to the window set by `with_history`
+    ///
+    /// Otherwise, this is as [`StreamScanner::close_with`].
+    pub fn close_with_history<F, R>(self, mut on_match: F) -> R::Output
+    where
+        F: FnMut(u32, u64, u64, u32, &History<'_>) -> R,
+        R: CallbackResult,
+    {
+        let history = self.history.view(&[]);
+        self.stream.close_with(self.scratch, |id, from, to, flags| {
+            on_match(id, from, to, flags, &history)
+        })
+    }
+
+    /// Scan the next chunk of input, returning all of the matches that end within it
+    pub fn find_all(&mut self, data: &[u8]) -> Result<Vec<Match>, Error> {
+        let mut matches = Vec::new();
+        self.scan(data, collect_matches(&mut matches))?;
+        Ok(matches)
+    }
+
+    /// Close the stream, returning all of the matches at the end of the stream
+    pub fn close_find_all(self) -> Result<Vec<Match>, Error> {
+        let mut matches = Vec::new();
+        self.close_with(collect_matches(&mut matches))?;
+        Ok(matches)
+    }
+
+    /// Close the stream using `hs_close_stream`, without processing matches at the end of the
+    /// stream.
+    ///
+    /// This is equivalent to dropping this object, but reports any error from Vectorscan.
+    pub fn discard(self) -> Result<(), Error> {
+        self.stream.discard()
+    }
+}
+
+/// The buffer size used by `StreamingScanner::scan_reader`
+pub const DEFAULT_READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The result of scanning everything from a reader, such as with
+/// [`StreamingScanner::scan_reader`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReaderScan<T> {
+    /// The number of bytes read and scanned
+    pub bytes: u64,
+
+    /// The outcome of the scan, as returned by [`StreamScanner::scan`]
+    pub outcome: T,
+}
+
+impl<T, E> ReaderScan<Result<T, E>> {
+    /// Convert a scan with a `Result` outcome into a `Result` of a scan, for use with `?`
+    pub fn transpose(self) -> Result<ReaderScan<T>, E> {
+        Ok(ReaderScan {
+            bytes: self.bytes,
+            outcome: self.outcome?,
+        })
+    }
+}
+
+/// Scan everything read from the reader through the given stream, then close it, counting the
+/// bytes scanned in `bytes`
+fn scan_buf_reader<T, F>(
+    mut stream: StreamScanner,
+    mut reader: T,
+    bytes: &mut u64,
+    mut on_match: F,
+) -> Result<Scan, Error>
+where
+    T: BufRead,
+    F: FnMut(u32, u64, u64, u32) -> Scan,
+{
+    loop {
+        let buf = match reader.fill_buf() {
+            Ok([]) => break,
+            Ok(buf) => buf,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let length = buf.len();
+        let res = stream.scan(buf, &mut on_match)?;
+        reader.consume(length);
+        *bytes += length as u64;
+        if res == Scan::Terminate {
+            return Ok(res);
         }
     }
+    stream.close_with(&mut on_match)
+}
+
+// -------------------------------------------------------------------------------------------------
+// Owned Scanners
+// -------------------------------------------------------------------------------------------------
+
+/// A scanner like [`BlockScanner`] that owns a handle to its database
+///
+/// Databases are cheap to clone, as clones share the compiled database. Unlike `BlockScanner`,
+/// this type has no lifetime parameter, so it can be stored in long-lived
+/// structures or moved into spawned threads and tasks.
+#[derive(Clone, Debug)]
+pub struct OwnedBlockScanner {
+    scratch: Scratch,
+    db: BlockDatabase,
+}
+
+impl OwnedBlockScanner {
+    /// Create a new scanner with the given database
+    pub fn new(db: BlockDatabase) -> Result<Self, Error> {
+        Ok(Self {
+            scratch: Scratch::new(&db)?,
+            db,
+        })
+    }
+
+    /// Create a new scanner with the given database, using an existing scratch space
+    ///
+    /// Returns `Error::ScratchMismatch` if the scratch space was not allocated for the database.
+    pub fn with_scratch(db: BlockDatabase, scratch: Scratch) -> Result<Self, Error> {
+        scratch.check(&db)?;
+        Ok(Self { scratch, db })
+    }
+
+    /// Consume this scanner, returning its scratch space
+    pub fn into_scratch(self) -> Scratch {
+        self.scratch
+    }
+
+    /// Create a copy of this scanner, with its own scratch space
+    ///
+    /// Unlike `clone`, this returns an error instead of panicking if the scratch space cannot be
+    /// copied.
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        Ok(Self {
+            db: self.db.clone(),
+            scratch: self.scratch.try_clone()?,
+        })
+    }
+
+    /// Get the database of this scanner
+    pub fn database(&self) -> &BlockDatabase {
+        &self.db
+    }
+
+    /// Scan the input using the given callback function
+    ///
+    /// See [`BlockScanner::scan`] for details.
+    pub fn scan<F, R>(&mut self, data: &[u8], on_match: F) -> R::Output
+    where
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        block_scan(&self.db.inner, &mut self.scratch, data, on_match)
+    }
+
+    /// Scan the input, returning all of the matches
+    pub fn find_all(&mut self, data: &[u8]) -> Result<Vec<Match>, Error> {
+        let mut matches = Vec::new();
+        self.scan(data, collect_matches(&mut matches))?;
+        Ok(matches)
+    }
+}
+
+/// A scanner like [`StreamingScanner`] that owns a handle to its database
+///
+/// Databases are cheap to clone, as clones share the compiled database. Unlike
+/// `StreamingScanner`, this type has no lifetime parameter, so it can be stored in
+/// long-lived structures or moved into spawned threads and tasks.
+#[derive(Clone, Debug)]
+pub struct OwnedStreamingScanner {
+    scratch: Scratch,
+    db: StreamingDatabase,
+}
+
+impl OwnedStreamingScanner {
+    /// Create a new scanner with the given database
+    pub fn new(db: StreamingDatabase) -> Result<Self, Error> {
+        Ok(Self {
+            scratch: Scratch::new(&db)?,
+            db,
+        })
+    }
+
+    /// Create a new scanner with the given database, using an existing scratch space
+    ///
+    /// Returns `Error::ScratchMismatch` if the scratch space was not allocated for the database.
+    pub fn with_scratch(db: StreamingDatabase, scratch: Scratch) -> Result<Self, Error> {
+        scratch.check(&db)?;
+        Ok(Self { scratch, db })
+    }
+
+    /// Consume this scanner, returning its scratch space
+    pub fn into_scratch(self) -> Scratch {
+        self.scratch
+    }
+
+    /// Create a copy of this scanner, with its own scratch space
+    ///
+    /// Unlike `clone`, this returns an error instead of panicking if the scratch space cannot be
+    /// copied.
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        Ok(Self {
+            db: self.db.clone(),
+            scratch: self.scratch.try_clone()?,
+        })
+    }
+
+    /// Get the database of this scanner
+    pub fn database(&self) -> &StreamingDatabase {
+        &self.db
+    }
+
+    /// Open a new stream using `hs_open_stream`
+    ///
+    /// The returned stream owns a copy of this scanner, including a copy of its scratch space
+    /// made with `hs_clone_scratch`, so that several streams can be open at once. Each stream
+    /// costs a scratch allocation in addition to the stream state; to open a single stream
+    /// without copying the scratch space, use `into_stream`.
+    pub fn open_stream(&self) -> Result<OwnedStreamScanner, Error> {
+        self.try_clone()?.into_stream()
+    }
+
+    /// Open a new stream using `hs_open_stream`, consuming this scanner
+    pub fn into_stream(self) -> Result<OwnedStreamScanner, Error> {
+        let stream = Stream::new(&self.db)?;
+        Ok(OwnedStreamScanner {
+            scanner: self,
+            stream,
+        })
+    }
+}
+
+/// A stream like [`StreamScanner`] that owns its scanner
+///
+/// Unlike `StreamScanner`, this type has no lifetime parameter, so it can be stored in
+/// long-lived structures or moved into spawned threads and tasks.
+#[derive(Debug)]
+pub struct OwnedStreamScanner {
+    scanner: OwnedStreamingScanner,
+    stream: Stream,
+}
+
+impl OwnedStreamScanner {
+    /// Open a new stream for the given database
+    pub fn new(db: StreamingDatabase) -> Result<Self, Error> {
+        OwnedStreamingScanner::new(db)?.into_stream()
+    }
+
+    /// Get the database of this stream
+    pub fn database(&self) -> &StreamingDatabase {
+        &self.scanner.db
+    }
+
+    /// Scan the next chunk of input using the given callback function
+    ///
+    /// See [`StreamScanner::scan`] for details.
+    pub fn scan<F, R>(&mut self, data: &[u8], on_match: F) -> R::Output
+    where
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        self.stream.scan(&mut self.scanner.scratch, data, on_match)
+    }
+
+    /// Scan the next chunk of input, returning all of the matches that end within it
+    pub fn find_all(&mut self, data: &[u8]) -> Result<Vec<Match>, Error> {
+        let mut matches = Vec::new();
+        self.scan(data, collect_matches(&mut matches))?;
+        Ok(matches)
+    }
+
+    /// Close the stream using `hs_close_stream`
+    ///
+    /// See [`StreamScanner::close_with`] for details.
+    pub fn close_with<F, R>(mut self, on_match: F) -> R::Output
+    where
+        F: FnMut(u32, u64, u64, u32) -> R,
+        R: CallbackResult,
+    {
+        self.stream.close_with(&mut self.scanner.scratch, on_match)
+    }
+
+    /// Close the stream, returning all of the matches at the end of the stream
+    pub fn close_find_all(self) -> Result<Vec<Match>, Error> {
+        let mut matches = Vec::new();
+        self.close_with(collect_matches(&mut matches))?;
+        Ok(matches)
+    }
+
+    /// Close the stream using `hs_close_stream`, without processing matches at the end of the
+    /// stream.
+    pub fn discard(self) -> Result<(), Error> {
+        self.stream.discard()
+    }
 }
 
 // -------------------------------------------------------------------------------------------------
@@ -277,14 +1441,57 @@ impl<'ss> StreamScanner<'ss> {
 ///
 /// This serves to wrap a Rust closure with a layer of indirection, so it can be referred to
 /// through a `void *` pointer in C.
-struct Context<F>
+struct Context<F, R>
 where
-    F: FnMut(u32, u64, u64, u32) -> Scan,
+    F: FnMut(u32, u64, u64, u32) -> R,
+    R: CallbackResult,
 {
     on_match: F,
+
+    /// The value returned by `on_match` that stopped the scan, if any
+    stopped: Option<R>,
+
+    /// The payload of a panic raised by `on_match`, if any
+    ///
+    /// Unwinding out of an `extern "C"` function aborts the process, so a panic in the callback
+    /// is caught in the trampoline, the scan is terminated, and the panic is resumed once control
+    /// has returned to Rust.
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+impl<F, R> Context<F, R>
+where
+    F: FnMut(u32, u64, u64, u32) -> R,
+    R: CallbackResult,
+{
+    fn new(on_match: F) -> Self {
+        Self {
+            on_match,
+            stopped: None,
+            panic: None,
+        }
+    }
+
+    /// Interpret the result of a Hyperscan matching API call that used this context
+    ///
+    /// If the callback panicked, the panic is resumed here.
+    fn finish(self, res: Result<(), Error>) -> R::Output {
+        if let Some(payload) = self.panic {
+            panic::resume_unwind(payload);
+        }
+
+        let res = match res {
+            Ok(_) => Ok(Scan::Continue),
+            Err(err) => match err {
+                Error::Hyperscan(HyperscanErrorCode::ScanTerminated, _) => Ok(Scan::Terminate),
+                err => Err(err),
+            },
+        };
+        R::into_output(self.stopped, res)
+    }
 }
 
-unsafe extern "C" fn on_match_trampoline<F>(
+unsafe extern "C" fn on_match_trampoline<F, R>(
     id: c_uint,
     from: c_ulonglong,
     to: c_ulonglong,
@@ -292,13 +1499,22 @@ unsafe extern "C" fn on_match_trampoline<F>(
     ctx: *mut c_void,
 ) -> c_int
 where
-    F: FnMut(u32, u64, u64, u32) -> Scan,
+    F: FnMut(u32, u64, u64, u32) -> R,
+    R: CallbackResult,
 {
-    let context = (ctx as *mut Context<F>)
+    let context = (ctx as *mut Context<F, R>)
         .as_mut()
         .expect("context object should be set");
-    match (context.on_match)(id, from, to, flags) {
-        Scan::Continue => 0,
-        Scan::Terminate => 1,
+    let on_match = &mut context.on_match;
+    match panic::catch_unwind(AssertUnwindSafe(|| on_match(id, from, to, flags))) {
+        Ok(result) if result.should_continue() => 0,
+        Ok(result) => {
+            context.stopped = Some(result);
+            1
+        }
+        Err(payload) => {
+            context.panic = Some(payload);
+            1
+        }
     }
 }
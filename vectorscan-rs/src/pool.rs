@@ -0,0 +1,340 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use vectorscan_rs_sys as hs;
+
+use super::{Database, Error, HyperscanErrorCode, Scratch};
+
+// -------------------------------------------------------------------------------------------------
+// Scratch Pool
+// -------------------------------------------------------------------------------------------------
+
+/// A pool of scratch spaces for a database, for scanning from several threads or tasks
+///
+/// Scratch spaces are handed out as [`ScratchGuard`]s, which return the scratch space to the pool
+/// when dropped. The pool keeps its databases alive, and grows on demand by allocating scratch
+/// spaces for them, up to an optional maximum size.
+///
+/// A pool can be shared between threads, e.g., using an `Arc<ScratchPool>`. The `_owned` methods
+/// on such a pool hand out [`OwnedScratchGuard`]s, which can be moved into spawned threads or
+/// tasks.
+pub struct ScratchPool {
+    /// The databases that scratch spaces are allocated for
+    databases: Vec<Box<dyn Database + Send + Sync>>,
+    max_size: Option<usize>,
+    state: Mutex<PoolState>,
+    returned: Condvar,
+    /// Wakes tasks waiting in `acquire` when a scratch space is returned
+    #[cfg(feature = "tokio")]
+    returned_async: tokio::sync::Notify,
+}
+
+#[derive(Debug)]
+struct PoolState {
+    /// Scratch spaces that are not currently handed out
+    idle: Vec<Scratch>,
+    /// The number of scratch spaces that have been created, including those handed out
+    size: usize,
+}
+
+impl ScratchPool {
+    /// Create a new pool for the given database, with no maximum size
+    pub fn new<D: Database>(db: &D) -> Result<Self, Error> {
+        Self::for_databases(&[db], None)
+    }
+
+    /// Create a new pool for the given database that holds at most `max_size` scratch spaces
+    ///
+    /// Once `max_size` scratch spaces are in use, `get` blocks until one is returned, and
+    /// `try_get` returns `None`. Returns `Error::Hyperscan` with `HyperscanErrorCode::Invalid` if
+    /// `max_size` is 0.
+    pub fn with_max_size<D: Database>(db: &D, max_size: usize) -> Result<Self, Error> {
+        Self::for_databases(&[db], Some(max_size))
+    }
+
+    /// Create a new pool whose scratch spaces can be used with any of the given databases
+    ///
+    /// Returns `Error::Hyperscan` with `HyperscanErrorCode::Invalid` if no databases are given or
+    /// if `max_size` is 0.
+    pub fn for_databases(dbs: &[&dyn Database], max_size: Option<usize>) -> Result<Self, Error> {
+        if max_size == Some(0) {
+            return Err(Error::Hyperscan(
+                HyperscanErrorCode::Invalid,
+                hs::HS_INVALID,
+            ));
+        }
+        // The first scratch space is allocated up front, so that allocation errors are reported
+        // here; it is the first one handed out.
+        let scratch = Scratch::for_databases(dbs)?;
+        Ok(Self {
+            databases: dbs.iter().map(|db| db.boxed()).collect(),
+            max_size,
+            state: Mutex::new(PoolState {
+                idle: vec![scratch],
+                size: 1,
+            }),
+            returned: Condvar::new(),
+            #[cfg(feature = "tokio")]
+            returned_async: tokio::sync::Notify::new(),
+        })
+    }
+
+    /// Get the maximum number of scratch spaces in this pool, if any
+    pub fn max_size(&self) -> Option<usize> {
+        self.max_size
+    }
+
+    /// Get the number of scratch spaces that have been created by this pool
+    pub fn size(&self) -> usize {
+        self.lock().size
+    }
+
+    /// Check whether scratch spaces from this pool can be used with the given database
+    pub fn is_valid_for<D: Database>(&self, db: &D) -> bool {
+        self.databases.iter().any(|d| d.id() == db.id())
+    }
+
+    /// Get a scratch space from the pool, creating one if none are idle
+    ///
+    /// If the pool has reached its maximum size, this blocks the current thread until a scratch
+    /// space is returned. In async code, prefer `acquire` (with the `tokio` feature) or `try_get`.
+    pub fn get(&self) -> Result<ScratchGuard<'_>, Error> {
+        self.get_scratch().map(|scratch| self.guard(scratch))
+    }
+
+    /// Get a scratch space from the pool without blocking
+    ///
+    /// Returns `None` if the pool has reached its maximum size and all of its scratch spaces are
+    /// in use.
+    pub fn try_get(&self) -> Result<Option<ScratchGuard<'_>>, Error> {
+        Ok(self.try_get_scratch()?.map(|scratch| self.guard(scratch)))
+    }
+
+    /// Like `get`, but the returned guard holds a reference to the pool instead of borrowing it
+    pub fn get_owned(self: &Arc<Self>) -> Result<OwnedScratchGuard, Error> {
+        self.get_scratch()
+            .map(|scratch| OwnedScratchGuard::new(self.clone(), scratch))
+    }
+
+    /// Like `try_get`, but the returned guard holds a reference to the pool instead of borrowing it
+    pub fn try_get_owned(self: &Arc<Self>) -> Result<Option<OwnedScratchGuard>, Error> {
+        Ok(self
+            .try_get_scratch()?
+            .map(|scratch| OwnedScratchGuard::new(self.clone(), scratch)))
+    }
+
+    /// Get a scratch space from the pool, creating one if none are idle
+    ///
+    /// If the pool has reached its maximum size, this waits for a scratch space to be returned
+    /// without blocking the current thread.
+    #[cfg(feature = "tokio")]
+    pub async fn acquire(&self) -> Result<ScratchGuard<'_>, Error> {
+        self.acquire_scratch()
+            .await
+            .map(|scratch| self.guard(scratch))
+    }
+
+    /// Like `acquire`, but the returned guard holds a reference to the pool instead of borrowing
+    /// it, so that it can be moved into a spawned task
+    #[cfg(feature = "tokio")]
+    pub async fn acquire_owned(self: &Arc<Self>) -> Result<OwnedScratchGuard, Error> {
+        self.acquire_scratch()
+            .await
+            .map(|scratch| OwnedScratchGuard::new(self.clone(), scratch))
+    }
+
+    fn get_scratch(&self) -> Result<Scratch, Error> {
+        let mut state = self.lock();
+        loop {
+            match self.take_or_reserve(&mut state) {
+                Slot::Idle(scratch) => return Ok(scratch),
+                Slot::Reserved => {
+                    drop(state);
+                    return self.create();
+                }
+                Slot::Exhausted => {
+                    state = self
+                        .returned
+                        .wait(state)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+            }
+        }
+    }
+
+    fn try_get_scratch(&self) -> Result<Option<Scratch>, Error> {
+        let mut state = self.lock();
+        match self.take_or_reserve(&mut state) {
+            Slot::Idle(scratch) => Ok(Some(scratch)),
+            Slot::Reserved => {
+                drop(state);
+                self.create().map(Some)
+            }
+            Slot::Exhausted => Ok(None),
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    async fn acquire_scratch(&self) -> Result<Scratch, Error> {
+        loop {
+            // Register interest before checking the pool, so that a scratch space returned in
+            // between is not missed
+            let mut notified = std::pin::pin!(self.returned_async.notified());
+            notified.as_mut().enable();
+            if let Some(scratch) = self.try_get_scratch()? {
+                return Ok(scratch);
+            }
+            notified.await;
+        }
+    }
+
+    /// Take an idle scratch space, or reserve room for a new one
+    fn take_or_reserve(&self, state: &mut PoolState) -> Slot {
+        if let Some(scratch) = state.idle.pop() {
+            Slot::Idle(scratch)
+        } else if self.max_size.map_or(true, |max| state.size < max) {
+            state.size += 1;
+            Slot::Reserved
+        } else {
+            Slot::Exhausted
+        }
+    }
+
+    /// Create a new scratch space, for which room has already been reserved
+    fn create(&self) -> Result<Scratch, Error> {
+        let dbs: Vec<&dyn Database> = self.databases.iter().map(|db| db.as_ref() as _).collect();
+        Scratch::for_databases(&dbs).map_err(|e| {
+            self.lock().size -= 1;
+            self.notify_returned();
+            e
+        })
+    }
+
+    /// Return a scratch space to the pool
+    fn put(&self, scratch: Scratch) {
+        self.lock().idle.push(scratch);
+        self.notify_returned();
+    }
+
+    /// Wake one thread or task waiting for a scratch space
+    fn notify_returned(&self) {
+        self.returned.notify_one();
+        #[cfg(feature = "tokio")]
+        self.returned_async.notify_one();
+    }
+
+    fn guard(&self, scratch: Scratch) -> ScratchGuard<'_> {
+        ScratchGuard {
+            pool: self,
+            scratch: Some(scratch),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        // The state is never left inconsistent while the lock is held, so poisoning can be ignored
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for ScratchPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScratchPool")
+            .field("max_size", &self.max_size)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The outcome of `ScratchPool::take_or_reserve`
+enum Slot {
+    /// An idle scratch space was taken from the pool
+    Idle(Scratch),
+    /// Room was reserved for a new scratch space, which the caller must create
+    Reserved,
+    /// The pool has reached its maximum size and all of its scratch spaces are in use
+    Exhausted,
+}
+
+/// A scratch space borrowed from a [`ScratchPool`], which is returned to the pool when dropped
+#[derive(Debug)]
+pub struct ScratchGuard<'p> {
+    pool: &'p ScratchPool,
+    scratch: Option<Scratch>,
+}
+
+impl Deref for ScratchGuard<'_> {
+    type Target = Scratch;
+
+    fn deref(&self) -> &Scratch {
+        self.scratch
+            .as_ref()
+            .expect("scratch is present until drop")
+    }
+}
+
+impl DerefMut for ScratchGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Scratch {
+        self.scratch
+            .as_mut()
+            .expect("scratch is present until drop")
+    }
+}
+
+impl Drop for ScratchGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(scratch) = self.scratch.take() {
+            self.pool.put(scratch);
+        }
+    }
+}
+
+/// A scratch space taken from a shared [`ScratchPool`], which is returned to the pool when
+/// dropped
+///
+/// Unlike [`ScratchGuard`], this does not borrow the pool, so it can be moved into a spawned
+/// thread or task.
+#[derive(Debug)]
+pub struct OwnedScratchGuard {
+    pool: Arc<ScratchPool>,
+    scratch: Option<Scratch>,
+}
+
+impl OwnedScratchGuard {
+    fn new(pool: Arc<ScratchPool>, scratch: Scratch) -> Self {
+        Self {
+            pool,
+            scratch: Some(scratch),
+        }
+    }
+
+    /// Get the pool that this scratch space belongs to
+    pub fn pool(&self) -> &Arc<ScratchPool> {
+        &self.pool
+    }
+}
+
+impl Deref for OwnedScratchGuard {
+    type Target = Scratch;
+
+    fn deref(&self) -> &Scratch {
+        self.scratch
+            .as_ref()
+            .expect("scratch is present until drop")
+    }
+}
+
+impl DerefMut for OwnedScratchGuard {
+    fn deref_mut(&mut self) -> &mut Scratch {
+        self.scratch
+            .as_mut()
+            .expect("scratch is present until drop")
+    }
+}
+
+impl Drop for OwnedScratchGuard {
+    fn drop(&mut self) {
+        if let Some(scratch) = self.scratch.take() {
+            self.pool.put(scratch);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::error::{AsResult, Error};
+use crate::error::{report_free_failure, AsResult, Error};
 use bitflags::bitflags;
 use foreign_types::{foreign_type, ForeignType};
 use std::{ffi::CString, mem::MaybeUninit, ptr};
@@ -27,21 +27,21 @@ foreign_type! {
 unsafe fn database_drop(v: *mut hs::hs_database_t) {
     let res = hs::hs_free_database(v);
     if res != hs::HS_SUCCESS as hs::hs_error_t {
-        panic!("hs_free_database failed: {res}");
+        report_free_failure("hs_free_database", res);
     }
 }
 
 unsafe fn scratch_drop(v: *mut hs::hs_scratch_t) {
     let res = hs::hs_free_scratch(v);
     if res != hs::HS_SUCCESS as hs::hs_error_t {
-        panic!("hs_free_scratch failed: {res}");
+        report_free_failure("hs_free_scratch", res);
     }
 }
 
 unsafe fn compile_error_drop(v: *mut hs::hs_compile_error_t) {
     let res = hs::hs_free_compile_error(v);
     if res != hs::HS_SUCCESS as hs::hs_error_t {
-        panic!("hs_free_compile_error failed: {res}");
+        report_free_failure("hs_free_compile_error", res);
     }
 }
 
@@ -77,6 +77,64 @@ impl Pattern {
             id,
         }
     }
+
+    /// Gets the flags of the pattern.
+    pub fn flags(&self) -> Flag {
+        self.flags
+    }
+
+    /// Gets information about the pattern using `hs_expression_info`.
+    pub fn info(&self) -> Result<ExpressionInfo, Error> {
+        let expression = CString::new(self.expression.clone())?;
+        let mut info = MaybeUninit::zeroed();
+        let mut err = MaybeUninit::zeroed();
+        unsafe {
+            hs::hs_expression_info(
+                expression.as_ptr(),
+                self.flags.bits(),
+                info.as_mut_ptr(),
+                err.as_mut_ptr(),
+            )
+            .ok()
+            .map_err(|_e| {
+                // The details of error value `_e` are stored in `err`; convert that and ignore `_e`
+                let err = CompileError::from_ptr(err.assume_init());
+                Error::HyperscanCompile(err.message(), err.expression())
+            })?;
+
+            let info: *mut hs::hs_expr_info_t = info.assume_init();
+            let result = ExpressionInfo {
+                min_width: (*info).min_width,
+                max_width: Some((*info).max_width).filter(|&w| w != u32::MAX),
+                unordered_matches: (*info).unordered_matches != 0,
+                matches_at_eod: (*info).matches_at_eod != 0,
+                matches_only_at_eod: (*info).matches_only_at_eod != 0,
+            };
+            // XXX should technically call the deallocator function set in `hs_set_misc_allocator`,
+            // but we never call that here, and the defaults are malloc/free
+            libc::free(info as *mut libc::c_void);
+            Ok(result)
+        }
+    }
+}
+
+/// Information about a pattern, as reported by `hs_expression_info`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ExpressionInfo {
+    /// The minimum length in bytes of a match
+    pub min_width: u32,
+
+    /// The maximum length in bytes of a match, or `None` if it is unbounded
+    pub max_width: Option<u32>,
+
+    /// Whether the pattern can produce matches that are not reported in order
+    pub unordered_matches: bool,
+
+    /// Whether the pattern can produce matches at the end of the data
+    pub matches_at_eod: bool,
+
+    /// Whether the pattern can only produce matches at the end of the data
+    pub matches_only_at_eod: bool,
 }
 
 impl Database {
@@ -148,6 +206,11 @@ impl Database {
         }
     }
 
+    /// Creates a deep copy of the database via serialization followed by deserialization.
+    pub fn deep_copy(&self) -> Result<Self, Error> {
+        self.serialize()?.deserialize()
+    }
+
     /// Gets the size of the database in bytes using `hs_database_size`.
     pub fn size(&self) -> Result<usize, Error> {
         let mut database_size = MaybeUninit::zeroed();
@@ -169,13 +232,6 @@ impl Database {
     }
 }
 
-/// Creates a deep copy of the database via serialization followed by deserialization.
-impl Clone for Database {
-    fn clone(&self) -> Self {
-        self.serialize().unwrap().deserialize().unwrap()
-    }
-}
-
 #[derive(Debug)]
 pub struct SerializedDatabase {
     bytes: *mut std::os::raw::c_char,
@@ -210,23 +266,36 @@ impl Drop for SerializedDatabase {
     }
 }
 
-impl Clone for Scratch {
-    fn clone(&self) -> Self {
+impl Scratch {
+    pub fn new(database: &Database) -> Result<Self, Error> {
         let mut scratch = MaybeUninit::zeroed();
         unsafe {
-            hs::hs_clone_scratch(self.0.as_ptr(), scratch.as_mut_ptr())
+            hs::hs_alloc_scratch(database.as_ptr(), scratch.as_mut_ptr())
                 .ok()
                 .map(|()| Scratch::from_ptr(scratch.assume_init()))
-                .unwrap()
         }
     }
-}
 
-impl Scratch {
-    pub fn new(database: &Database) -> Result<Self, Error> {
+    /// Grows the scratch space so that it can also be used with `database`, using
+    /// `hs_alloc_scratch`.
+    ///
+    /// The scratch space may be reallocated, so it is consumed; on failure, it is freed.
+    pub fn grow(self, database: &Database) -> Result<Self, Error> {
+        let mut scratch = self.into_ptr();
+        unsafe {
+            let res = hs::hs_alloc_scratch(database.as_ptr(), &mut scratch).ok();
+            // On allocation failure, `hs_alloc_scratch` has already freed the scratch space and
+            // set it to null; on other failures, it is left untouched, and is freed here.
+            let scratch = (!scratch.is_null()).then(|| Scratch::from_ptr(scratch));
+            res.map(|()| scratch.expect("hs_alloc_scratch succeeded without a scratch space"))
+        }
+    }
+
+    /// Creates a copy of the scratch space using `hs_clone_scratch`.
+    pub fn try_clone(&self) -> Result<Self, Error> {
         let mut scratch = MaybeUninit::zeroed();
         unsafe {
-            hs::hs_alloc_scratch(database.as_ptr(), scratch.as_mut_ptr())
+            hs::hs_clone_scratch(self.0.as_ptr(), scratch.as_mut_ptr())
                 .ok()
                 .map(|()| Scratch::from_ptr(scratch.assume_init()))
         }
@@ -249,9 +318,8 @@ impl CompileError {
             let err = self.0.as_ptr();
 
             std::ffi::CStr::from_ptr((*err).message)
-                .to_str()
-                .unwrap()
-                .into()
+                .to_string_lossy()
+                .into_owned()
         }
     }
     fn expression(&self) -> i32 {
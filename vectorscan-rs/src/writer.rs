@@ -0,0 +1,160 @@
+use std::io::{self, Write};
+use std::sync::mpsc::Sender;
+
+use super::{CallbackResult, Error, Match, Scan, StreamScanner};
+
+// -------------------------------------------------------------------------------------------------
+// Scan Writer
+// -------------------------------------------------------------------------------------------------
+
+/// An adapter that implements `std::io::Write` by scanning everything written to it through a
+/// stream
+///
+/// Written bytes are first forwarded to an inner writer, and the bytes it accepted are then
+/// scanned, with matches delivered to a callback function. This allows scanning to be added to
+/// code that writes to an `io::Write`, e.g., using `io::copy`, without buffering the data.
+///
+/// Call `finish` once everything has been written, so that matches at the end of the stream
+/// are reported. If the callback function stops the scan, the remaining data is still forwarded
+/// to the inner writer, but is not scanned.
+///
+/// Once bytes have been accepted by the inner writer, `write` reports them as written even if
+/// scanning them fails, so that they are not forwarded twice when the caller retries. Scanning
+/// stops at the first error, which is returned by `finish`.
+pub struct ScanWriter<'ss, W, F, R = Scan> {
+    stream: StreamScanner<'ss>,
+    inner: W,
+    on_match: F,
+    /// The value returned by the callback function that stopped the scan, if any
+    stopped: Option<R>,
+    /// The error that stopped the scan, if any, to be returned by `finish`
+    error: Option<Error>,
+    terminated: bool,
+}
+
+impl<'ss, F, R> ScanWriter<'ss, io::Sink, F, R>
+where
+    F: FnMut(u32, u64, u64, u32) -> R,
+    R: CallbackResult,
+{
+    /// Create a writer that scans everything written to it through the given stream, without
+    /// forwarding it anywhere
+    pub fn new(stream: StreamScanner<'ss>, on_match: F) -> Self {
+        Self::with_inner(stream, io::sink(), on_match)
+    }
+}
+
+impl<'ss, W> ScanWriter<'ss, W, ()> {
+    /// Create a writer that forwards everything written to it to `inner` and scans it through the
+    /// given stream, sending each match through the given channel
+    ///
+    /// Scanning continues if the receiving end of the channel has been dropped.
+    pub fn with_sender(
+        stream: StreamScanner<'ss>,
+        inner: W,
+        sender: Sender<Match>,
+    ) -> ScanWriter<'ss, W, impl FnMut(u32, u64, u64, u32) -> Scan>
+    where
+        W: Write,
+    {
+        ScanWriter::with_inner(stream, inner, move |id, from, to, _flags| {
+            let _ = sender.send(Match::new(id, from, to));
+            Scan::Continue
+        })
+    }
+}
+
+impl<'ss, W, F, R> ScanWriter<'ss, W, F, R>
+where
+    W: Write,
+    F: FnMut(u32, u64, u64, u32) -> R,
+    R: CallbackResult,
+{
+    /// Create a writer that forwards everything written to it to `inner` and scans it through the
+    /// given stream
+    ///
+    /// The callback function is as for [`StreamScanner::scan`].
+    pub fn with_inner(stream: StreamScanner<'ss>, inner: W, on_match: F) -> Self {
+        Self {
+            stream,
+            inner,
+            on_match,
+            stopped: None,
+            error: None,
+            terminated: false,
+        }
+    }
+
+    /// Get a reference to the inner writer
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Close the stream, reporting any matches at the end of the stream, and return the inner
+    /// writer along with the outcome of the scan
+    ///
+    /// The outcome is what [`StreamScanner::scan`] would return for the whole stream, including
+    /// any error that stopped scanning during a `write`. The inner writer is not flushed.
+    pub fn finish(self) -> (W, R::Output) {
+        let Self {
+            stream,
+            inner,
+            mut on_match,
+            mut stopped,
+            error,
+            terminated,
+        } = self;
+        let res = match error {
+            Some(e) => Err(e),
+            None if terminated => stream.discard().map(|()| Scan::Terminate),
+            None => stream.close_with(stop_on(&mut on_match, &mut stopped)),
+        };
+        (inner, R::into_output(stopped, res))
+    }
+}
+
+impl<W, F, R> Write for ScanWriter<'_, W, F, R>
+where
+    W: Write,
+    F: FnMut(u32, u64, u64, u32) -> R,
+    R: CallbackResult,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if !self.terminated {
+            let on_match = stop_on(&mut self.on_match, &mut self.stopped);
+            match self.stream.scan(&buf[..written], on_match) {
+                Ok(res) => self.terminated = res == Scan::Terminate,
+                Err(e) => {
+                    self.error = Some(e);
+                    self.terminated = true;
+                }
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Adapt a callback function to return `Scan`, storing the value that stopped the scan, if any
+fn stop_on<'a, F, R>(
+    on_match: &'a mut F,
+    stopped: &'a mut Option<R>,
+) -> impl FnMut(u32, u64, u64, u32) -> Scan + 'a
+where
+    F: FnMut(u32, u64, u64, u32) -> R,
+    R: CallbackResult,
+{
+    move |id, from, to, flags| {
+        let res = on_match(id, from, to, flags);
+        if res.should_continue() {
+            Scan::Continue
+        } else {
+            *stopped = Some(res);
+            Scan::Terminate
+        }
+    }
+}